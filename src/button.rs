@@ -0,0 +1,48 @@
+use acpi::AcpiHandler;
+use alloc::boxed::Box;
+
+use crate::{event::EventHandlerId, AcpiSystem, AcpiSystemError, EventAction, FixedEvent, Handler};
+
+const HID_SLEEP_BUTTON: &str = "PNP0C0E";
+
+// Notify() values sent to the control-method buttons
+const NOTIFY_BUTTON_PRESSED: u64 = 0x80;
+
+impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
+    /// Installs the sleep button handler. The button is either the fixed hardware one (SLPBTN_STS)
+    /// or, if the FADT says so, a control-method sleep button device (PNP0C0E) which reports the
+    /// button presses through Notify(0x80).
+    pub fn enable_sleep_button(
+        &mut self,
+        handler: Box<dyn FnMut(&mut Self) -> EventAction>,
+    ) -> Result<(), AcpiSystemError> {
+        let flags = self.fadt.flags;
+        if !flags.sleep_button_is_control_method() {
+            return self.enable_fixed_event(&FixedEvent::SLEEP_BUTTON, handler);
+        }
+
+        let devices = self.find_devices(HID_SLEEP_BUTTON)?;
+        if devices.is_empty() {
+            log::warn!("No control method sleep button found");
+        }
+
+        // All the button devices share the fixed event handler slot
+        self.event_handlers
+            .install(EventHandlerId::SleepButton, handler);
+        for device in devices {
+            log::info!("{}: control method sleep button", device);
+            self.set_notify_handler(
+                device,
+                Box::new(|system, value| {
+                    if value == NOTIFY_BUTTON_PRESSED {
+                        system.run_event_handler(EventHandlerId::SleepButton)
+                    } else {
+                        EventAction::Nothing
+                    }
+                }),
+            );
+        }
+
+        Ok(())
+    }
+}
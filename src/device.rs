@@ -0,0 +1,197 @@
+use acpi::AcpiHandler;
+use alloc::{format, string::String, vec, vec::Vec};
+use aml::{value::Args, AmlName, AmlValue};
+
+use crate::{namespace::device_id_from_value, AcpiSystem, AcpiSystemError, Handler};
+
+/// Evaluated _STA of a device
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeviceStatus(pub u64);
+
+impl DeviceStatus {
+    const PRESENT: u64 = 1 << 0;
+    const ENABLED: u64 = 1 << 1;
+    const SHOWN_IN_UI: u64 = 1 << 2;
+    const FUNCTIONING: u64 = 1 << 3;
+    const BATTERY_PRESENT: u64 = 1 << 4;
+
+    pub fn is_present(&self) -> bool {
+        self.0 & Self::PRESENT != 0
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0 & Self::ENABLED != 0
+    }
+
+    pub fn is_shown_in_ui(&self) -> bool {
+        self.0 & Self::SHOWN_IN_UI != 0
+    }
+
+    pub fn is_functioning(&self) -> bool {
+        self.0 & Self::FUNCTIONING != 0
+    }
+
+    /// Only meaningful for control method batteries
+    pub fn is_battery_present(&self) -> bool {
+        self.0 & Self::BATTERY_PRESENT != 0
+    }
+}
+
+/// A Device object in the AML namespace along with its identification objects
+#[derive(Clone, Debug)]
+pub struct AcpiDevice {
+    pub path: AmlName,
+    /// _HID, EISA IDs are decoded into their string form, e.g. "PNP0A08"
+    pub hid: Option<String>,
+    /// _CID, decoded the same way as _HID
+    pub cids: Vec<String>,
+    /// _UID, integer values are converted to decimal strings
+    pub uid: Option<String>,
+    /// _ADR, the address of the device on its parent bus
+    pub adr: Option<u64>,
+    pub status: DeviceStatus,
+}
+
+const PATH_SYSTEM_BUS_INIT: &str = "\\_SB._INI";
+
+/// Outcome of the _STA/_INI pass done by [AcpiSystem::initialize]
+#[derive(Debug, Default)]
+pub struct DeviceInitReport {
+    /// Number of the devices whose _INI has been evaluated successfully
+    pub initialized: usize,
+    /// Devices which are neither present nor functioning, their children were skipped
+    pub absent: Vec<AmlName>,
+    /// Devices whose _STA or _INI has failed, their children were still initialized
+    pub failures: Vec<(AmlName, AcpiSystemError)>,
+}
+
+impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
+    /// Returns the outcome of the device initialization done by [AcpiSystem::initialize]
+    pub fn device_init_report(&self) -> &DeviceInitReport {
+        &self.device_init_report
+    }
+
+    // AcpiNsInitializeDevices: \_SB._INI is evaluated first, then each device is initialized
+    // parent-first according to its _STA:
+    // * present - _INI is run and the children are initialized
+    // * functioning but not present - _INI is not run, but the children are initialized
+    // * otherwise - the whole subtree is skipped
+    // Like in ACPICA, a failing _STA or _INI is only reported, so that a single firmware bug
+    // doesn't hide the devices below.
+    pub(crate) fn initialize_devices(&mut self) -> Result<DeviceInitReport, AcpiSystemError> {
+        let mut report = DeviceInitReport::default();
+
+        let system_bus_init = AmlName::from_str(PATH_SYSTEM_BUS_INIT).unwrap();
+        if let Err(error) = self.invoke_optional_method(&system_bus_init, Args::EMPTY) {
+            log::warn!("{}: {:?}", system_bus_init, error);
+            report.failures.push((system_bus_init.clone(), error));
+        }
+
+        let mut skipped = Vec::new();
+        for device in self.device_paths()? {
+            // Device paths are listed parent-first, so the parent has already been looked at
+            let mut parent = device.parent();
+            let mut skip = false;
+            while let Ok(name) = parent {
+                if skipped.contains(&name) {
+                    skip = true;
+                    break;
+                }
+                parent = name.parent();
+            }
+            if skip {
+                continue;
+            }
+
+            let status = match self.device_status(&device) {
+                Ok(status) => DeviceStatus(status),
+                Err(error) => {
+                    log::warn!("{}._STA: {:?}", device, error);
+                    report.failures.push((device, error));
+                    continue;
+                }
+            };
+
+            if !status.is_present() {
+                if !status.is_functioning() {
+                    log::trace!("{} is not present", device);
+                    skipped.push(device.clone());
+                    report.absent.push(device);
+                }
+                continue;
+            }
+
+            let path = AmlName::from_str("_INI").unwrap().resolve(&device)?;
+            // Already done above in case \_SB is declared as a Device
+            if path == system_bus_init {
+                continue;
+            }
+            match self.invoke_optional_method(&path, Args::EMPTY) {
+                Ok(true) => report.initialized += 1,
+                Ok(false) => (),
+                Err(error) => {
+                    log::warn!("{}: {:?}", path, error);
+                    report.failures.push((device, error));
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Returns an iterator over all the Device objects in the namespace. Their identification
+    /// objects are evaluated as the iterator advances, objects which are missing or fail to
+    /// evaluate are reported as `None`.
+    pub fn devices(&mut self) -> Result<impl Iterator<Item = AcpiDevice> + '_, AcpiSystemError> {
+        let paths = self.device_paths()?;
+
+        Ok(paths.into_iter().map(move |path| self.device_info(path)))
+    }
+
+    fn device_info(&mut self, path: AmlName) -> AcpiDevice {
+        let hid = self.device_hid(&path).unwrap_or_else(|error| {
+            log::warn!("{}._HID: {:?}", path, error);
+            None
+        });
+        let cids = self
+            .device_object(&path, "_CID")
+            .map_or(vec![], |cid| match cid {
+                AmlValue::Package(elements) => elements
+                    .lock()
+                    .iter()
+                    .filter_map(device_id_from_value)
+                    .collect(),
+                value => device_id_from_value(&value).into_iter().collect(),
+            });
+        let uid = self.device_object(&path, "_UID").and_then(|uid| match uid {
+            AmlValue::Integer(uid) => Some(format!("{}", uid)),
+            AmlValue::String(uid) => Some(uid),
+            _ => None,
+        });
+        let adr = self
+            .device_object(&path, "_ADR")
+            .and_then(|adr| adr.as_integer(&self.aml_context).ok());
+        let status = self.device_status(&path).unwrap_or_else(|error| {
+            log::warn!("{}._STA: {:?}", path, error);
+            0
+        });
+
+        AcpiDevice {
+            path,
+            hid,
+            cids,
+            uid,
+            adr,
+            status: DeviceStatus(status),
+        }
+    }
+
+    // Evaluates an optional object of a device, logging the errors
+    fn device_object(&mut self, device: &AmlName, name: &str) -> Option<AmlValue> {
+        let path = AmlName::from_str(name).unwrap().resolve(device).ok()?;
+        self.evaluate_object(&path).unwrap_or_else(|error| {
+            log::warn!("{}.{}: {:?}", device, name, error);
+            None
+        })
+    }
+}
@@ -1,6 +1,8 @@
-use acpi::AcpiError;
+use acpi::{address::AddressSpace, AcpiError};
 use aml::AmlError;
 
+use crate::{AcpiSleepState, FirmwareWakingVector, RtcAlarm};
+
 #[derive(Debug)]
 pub enum AcpiSystemError {
     AcpiError(AcpiError),
@@ -8,10 +10,36 @@ pub enum AcpiSystemError {
 
     EnableTimeout,
     ModeTransitionNotSupported,
+    ResetNotSupported,
+    PciConfigNotSupported,
+    UnsupportedAddressSpace(AddressSpace),
+    PmTimerNotSupported,
+    NotSupportedOnReducedHardware,
 
     InvalidSleepValues(u8, u8),
+    UnsupportedWakeState(AcpiSleepState),
+    InvalidWakeDevice,
     InvalidSleepMethod(&'static str),
     MissingSleepMethod(&'static str),
+    ResumeNotSupported,
+    S4BiosNotSupported,
+    MissingSleepRegisters,
+
+    MissingFacs,
+    UnsupportedWakingVector(FirmwareWakingVector),
+
+    RtcAlarmNotSupported,
+    UnsupportedRtcAlarm(RtcAlarm),
+
+    InvalidGpe(u16),
+    InvalidGpeBlockDevice,
+    InvalidGedDevice,
+
+    OscFailed(u32),
+    InvalidOscResponse,
+
+    GlobalLockTimeout,
+    GlobalLockNotAcquired,
 }
 
 impl From<AcpiError> for AcpiSystemError {
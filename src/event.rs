@@ -1,9 +1,12 @@
 use acpi::{
-    address::{AccessSize, GenericAddress},
+    address::{AccessSize, AddressSpace, GenericAddress},
     AcpiHandler,
 };
 use alloc::{vec, vec::Vec};
-use enum_map::Enum;
+use aml::{
+    resource::{self, AddressSpaceResourceType, MemoryRangeDescriptor, Resource},
+    AmlName, AmlValue,
+};
 
 use crate::{
     hardware::{AcpiBitRegister, AcpiRegister},
@@ -12,27 +15,113 @@ use crate::{
 
 pub const GPE_REGISTER_WIDTH: usize = 8;
 
-#[allow(dead_code)]
+const PATH_GPE_SCOPE: &str = "\\_GPE";
+const HID_GPE_BLOCK_DEVICE: &str = "ACPI0006";
+
+/// Describes when the status bit of a GPE gets cleared during its dispatch
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum GpeTrigger {
+    /// Status is cleared before the GPE is handled
+    #[default]
+    Edge,
+    /// Status is cleared after the GPE is handled, as the source is only deasserted by the
+    /// handler itself
+    Level,
+}
+
+/// Describes the purpose of a GPE
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum GpeType {
+    /// The GPE is dispatched through the SCI while the system is working
+    #[default]
+    Runtime,
+    /// The GPE is only enabled while the system is sleeping and is used to wake it up
+    Wake,
+}
+
+/// Current state of a GPE, as returned by [AcpiSystem::gpe_status]
+#[derive(Clone, Copy, Debug)]
+pub struct GpeStatus {
+    pub gpe_type: GpeType,
+    pub trigger: GpeTrigger,
+    /// The GPE is enabled for its [GpeType]
+    pub enabled: bool,
+    /// The GPE's status bit is set in hardware
+    pub active: bool,
+    /// The GPE has either an OS handler or an _Lxx/_Exx method attached
+    pub has_handler: bool,
+}
+
 struct GpeRegisterInfo {
     base_gpe_number: u16,
     enable_register: GenericAddress,
     status_register: GenericAddress,
+    // Mask of GPEs enabled for runtime (SCI) dispatch
+    enable_for_run: u8,
+    // Mask of GPEs enabled for waking the system up
+    enable_for_wake: u8,
+    // Mask of runtime GPEs which are temporarily disabled while being dispatched
+    masked: u8,
+}
+
+impl GpeRegisterInfo {
+    // Value of the enable register at runtime
+    fn run_enable_value(&self) -> u8 {
+        self.enable_for_run & !self.masked
+    }
 }
 
-#[allow(dead_code)]
 struct GpeEventInfo {
     gpe_number: u16,
     register_index: usize,
+    gpe_type: GpeType,
+    trigger: GpeTrigger,
+    method: Option<AmlName>,
 }
 
-#[allow(dead_code)]
 pub(crate) struct GpeBlock {
+    base_gpe_number: u16,
+    interrupt_number: u32,
+    // GPE block device this block belongs to, None for FADT blocks
+    device: Option<AmlName>,
     register_info: Vec<GpeRegisterInfo>,
     event_info: Vec<GpeEventInfo>,
     gpe_count: usize,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Enum)]
+impl GpeBlock {
+    fn contains(&self, gpe_number: u16) -> bool {
+        gpe_number >= self.base_gpe_number
+            && ((gpe_number - self.base_gpe_number) as usize) < self.gpe_count
+    }
+
+    fn event_info(&self, gpe_number: u16) -> Option<&GpeEventInfo> {
+        if !self.contains(gpe_number) {
+            return None;
+        }
+        self.event_info
+            .get((gpe_number - self.base_gpe_number) as usize)
+    }
+
+    fn event_info_mut(&mut self, gpe_number: u16) -> Option<&mut GpeEventInfo> {
+        if !self.contains(gpe_number) {
+            return None;
+        }
+        self.event_info
+            .get_mut((gpe_number - self.base_gpe_number) as usize)
+    }
+
+    // Returns the index of the register containing the GPE and the GPE's bit within it
+    fn register_mask(&self, gpe_number: u16) -> Option<(usize, u8)> {
+        let info = self.event_info(gpe_number)?;
+        let register = &self.register_info[info.register_index];
+        let bit = gpe_number - register.base_gpe_number;
+
+        Some((info.register_index, 1 << bit))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub(crate) enum EventHandlerId {
     Timer,
     GlobalLock,
@@ -48,13 +137,91 @@ pub struct FixedEvent {
     pub(crate) handler_id: EventHandlerId,
 }
 
-// TODO event handlers cannot borrow AcpiSystem mutably, so some kind of "return token" has to be
-//      used instead
-#[derive(Clone, Copy, Debug, Default)]
+/// Response of an event handler, performed by the [AcpiSystem] once the handler returns
+#[derive(Clone, Debug, Default)]
 pub enum EventAction {
     #[default]
     Nothing,
     EnterSleepState(AcpiSleepState),
+    /// Resets the system through the FADT reset register
+    Reset,
+    /// Evaluates an AML method with the given arguments, its result is discarded
+    EvaluateMethod(AmlName, Vec<AmlValue>),
+    /// Delivers a notification to the notify handlers, as if AML code executed Notify()
+    Notify(AmlName, u64),
+    EnableGpe(u16),
+    DisableGpe(u16),
+    /// Token passed back to the OS through [AcpiSystem::poll_custom_event]
+    Custom(u32),
+    /// Performs the actions in order, stopping at the first one which fails
+    Sequence(Vec<EventAction>),
+}
+
+// Work deferred from the interrupt handlers to AcpiSystem::process_pending_events
+pub(crate) enum PendingEvent {
+    Fixed(EventHandlerId),
+    // The GPE stays masked until this is processed
+    Gpe(u16),
+    Ged { device: AmlName, irq: u32 },
+}
+
+// Event handlers by key. A handler is taken out of the table while it runs, so that it can access
+// the system. If a handler is installed or removed for the same key meanwhile, the running handler
+// is dropped afterwards instead of being put back.
+pub(crate) struct HandlerTable<K, F> {
+    handlers: Vec<(K, F)>,
+    // Keys of the running handlers, and whether they have been replaced or removed meanwhile
+    running: Vec<(K, bool)>,
+}
+
+impl<K: PartialEq, F> HandlerTable<K, F> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            handlers: Vec::new(),
+            running: Vec::new(),
+        }
+    }
+
+    pub(crate) fn contains(&self, key: &K) -> bool {
+        self.handlers.iter().any(|(other, _)| other == key)
+            || self
+                .running
+                .iter()
+                .any(|(other, changed)| other == key && !changed)
+    }
+
+    pub(crate) fn install(&mut self, key: K, handler: F) {
+        self.remove(&key);
+        self.handlers.push((key, handler));
+    }
+
+    pub(crate) fn remove(&mut self, key: &K) {
+        self.handlers.retain(|(other, _)| other != key);
+        for (other, changed) in self.running.iter_mut() {
+            if other == key {
+                *changed = true;
+            }
+        }
+    }
+
+    fn take(&mut self, key: &K) -> Option<F> {
+        let index = self.handlers.iter().position(|(other, _)| other == key)?;
+        let (key, handler) = self.handlers.remove(index);
+        self.running.push((key, false));
+        Some(handler)
+    }
+
+    fn restore(&mut self, key: &K, handler: F) {
+        let index = self
+            .running
+            .iter()
+            .rposition(|(other, _)| other == key)
+            .unwrap();
+        let (key, changed) = self.running.remove(index);
+        if !changed {
+            self.handlers.push((key, handler));
+        }
+    }
 }
 
 impl FixedEvent {
@@ -118,7 +285,14 @@ impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
     }
 
     fn initialize_fixed_events(&mut self) -> Result<(), AcpiSystemError> {
-        self.disable_fixed_events()
+        self.disable_fixed_events()?;
+
+        // The firmware signals the release of the global lock through GBL_STS
+        if self.facs.is_some() {
+            FixedEvent::GLOBAL_LOCK.enable_register.set(self, true)?;
+        }
+
+        Ok(())
     }
 
     fn install_sci_handler(&mut self) -> Result<(), AcpiSystemError> {
@@ -131,7 +305,7 @@ impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
         block_address: GenericAddress,
         register_count: usize,
         block_base_number: u16,
-        _interrupt_number: u32,
+        interrupt_number: u32,
     ) -> Result<GpeBlock, AcpiSystemError> {
         log::info!("GPE block #{}", block_base_number);
         log::info!("Block address: {:#x?}", block_address);
@@ -168,6 +342,9 @@ impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
                 event_info.push(GpeEventInfo {
                     gpe_number,
                     register_index: i,
+                    gpe_type: GpeType::default(),
+                    trigger: GpeTrigger::default(),
+                    method: None,
                 });
             }
 
@@ -181,10 +358,16 @@ impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
                 base_gpe_number,
                 status_register,
                 enable_register,
+                enable_for_run: 0,
+                enable_for_wake: 0,
+                masked: 0,
             });
         }
 
         Ok(GpeBlock {
+            base_gpe_number: block_base_number,
+            interrupt_number,
+            device: None,
             register_info,
             event_info,
             gpe_count,
@@ -197,7 +380,7 @@ impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
         //
         // GPE register width is 8 bits
 
-        let _gpe_number_max = if let Some(gpe0) = self.fadt.gpe0_block()? {
+        let gpe0_number_max = if let Some(gpe0) = self.fadt.gpe0_block()? {
             let reg_count = self.fadt.gpe0_block_length() as usize / 2;
             let gpe_number_max = (reg_count * GPE_REGISTER_WIDTH) - 1;
 
@@ -206,13 +389,219 @@ impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
                 self.initialize_gpe_block(gpe0, reg_count, 0, self.fadt.sci_interrupt as u32)?;
             self.gpe0_block.replace(block);
 
-            gpe_number_max
+            Some(gpe_number_max)
         } else {
-            0
+            None
         };
 
-        if let Some(_gpe1) = self.fadt.gpe1_block()? {
-            todo!()
+        if let Some(gpe1) = self.fadt.gpe1_block()? {
+            let reg_count = self.fadt.gpe1_block_length() as usize / 2;
+            let gpe1_base = self.fadt.gpe1_base as usize;
+            let gpe_number_max = gpe1_base + (reg_count * GPE_REGISTER_WIDTH) - 1;
+
+            // GPE1 numbers follow the GPE0 ones and must not overlap them. ACPICA ignores the GPE1
+            // block in such case, so do the same instead of failing the whole initialization.
+            if let Some(gpe0_number_max) = gpe0_number_max.filter(|&max| gpe1_base <= max) {
+                log::error!(
+                    "GPE0 block (#0..=#{:#x}) overlaps GPE1 block (#{:#x}..=#{:#x}), ignoring GPE1",
+                    gpe0_number_max,
+                    gpe1_base,
+                    gpe_number_max
+                );
+            } else if gpe_number_max > u8::MAX as usize {
+                log::error!(
+                    "GPE1 block (#{:#x}..=#{:#x}) exceeds the maximum GPE number, ignoring GPE1",
+                    gpe1_base,
+                    gpe_number_max
+                );
+            } else {
+                let block = self.initialize_gpe_block(
+                    gpe1,
+                    reg_count,
+                    gpe1_base as u16,
+                    self.fadt.sci_interrupt as u32,
+                )?;
+                self.gpe1_block.replace(block);
+            }
+        }
+
+        // FADT GPE blocks' methods are located in \_GPE and are numbered globally
+        let scope = AmlName::from_str(PATH_GPE_SCOPE).unwrap();
+        self.initialize_gpe_methods(&scope, 0)
+    }
+
+    // GPE block devices describe additional GPE blocks through their _CRS. Their GPEs are numbered
+    // after the ones already known, while their _Lxx/_Exx methods are located within the device
+    // itself and are numbered relative to the block.
+    pub(crate) fn initialize_gpe_block_devices(&mut self) -> Result<(), AcpiSystemError> {
+        for device in self.find_devices(HID_GPE_BLOCK_DEVICE)? {
+            if let Err(error) = self.initialize_gpe_block_device(&device) {
+                log::warn!("{}: {:?}", device, error);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn initialize_gpe_block_device(&mut self, device: &AmlName) -> Result<(), AcpiSystemError> {
+        let path = AmlName::from_str("_CRS").unwrap().resolve(device)?;
+        let crs = self
+            .evaluate_object(&path)?
+            .ok_or(AcpiSystemError::InvalidGpeBlockDevice)?;
+
+        // The first I/O or memory resource describes the GPEx_STS + GPEx_EN register pair array,
+        // an optional interrupt resource routes the block away from the SCI
+        let mut registers = None;
+        let mut interrupt = None;
+
+        for resource in resource::resource_descriptor_list(&crs)? {
+            match resource {
+                Resource::IOPort(port) if registers.is_none() => {
+                    registers.replace((
+                        AddressSpace::SystemIo,
+                        port.memory_range.0 as u64,
+                        port.range_length as usize,
+                    ));
+                }
+                Resource::AddressSpace(space) if registers.is_none() => {
+                    let address_space = match space.resource_type {
+                        AddressSpaceResourceType::IORange => AddressSpace::SystemIo,
+                        AddressSpaceResourceType::MemoryRange => AddressSpace::SystemMemory,
+                        _ => continue,
+                    };
+                    registers.replace((
+                        address_space,
+                        space.address_range.0,
+                        space.length as usize,
+                    ));
+                }
+                Resource::MemoryRange(MemoryRangeDescriptor::FixedLocation {
+                    base_address,
+                    range_length,
+                    ..
+                }) if registers.is_none() => {
+                    registers.replace((
+                        AddressSpace::SystemMemory,
+                        base_address as u64,
+                        range_length as usize,
+                    ));
+                }
+                Resource::Irq(irq) if interrupt.is_none() => {
+                    interrupt.replace(irq.irq);
+                }
+                _ => (),
+            }
+        }
+
+        let (address_space, address, length) =
+            registers.ok_or(AcpiSystemError::InvalidGpeBlockDevice)?;
+        let register_count = length / 2;
+        if register_count == 0 {
+            return Err(AcpiSystemError::InvalidGpeBlockDevice);
+        }
+
+        let sci_interrupt = self.fadt.sci_interrupt as u32;
+        let interrupt_number = interrupt.unwrap_or(sci_interrupt);
+        let base_gpe_number = self.next_free_gpe_number();
+        let block_address = GenericAddress {
+            address,
+            address_space,
+            bit_width: GPE_REGISTER_WIDTH as u8,
+            bit_offset: 0,
+            access_size: AccessSize::Undefined,
+        };
+
+        log::info!("{}: GPE block device, IRQ {}", device, interrupt_number);
+        let mut block = self.initialize_gpe_block(
+            block_address,
+            register_count,
+            base_gpe_number,
+            interrupt_number,
+        )?;
+        block.device.replace(device.clone());
+        self.gpe_device_blocks.push(block);
+
+        if interrupt_number != sci_interrupt {
+            H::install_interrupt_handler(interrupt_number)?;
+        }
+
+        self.initialize_gpe_methods(device, base_gpe_number)
+    }
+
+    // Returns the number of the first GPE of a GPE block device
+    pub(crate) fn gpe_block_device_base(&self, device: &AmlName) -> Option<u16> {
+        self.gpe_device_blocks
+            .iter()
+            .find(|block| block.device.as_ref() == Some(device))
+            .map(|block| block.base_gpe_number)
+    }
+
+    fn next_free_gpe_number(&self) -> u16 {
+        self.gpe_blocks()
+            .map(|block| block.base_gpe_number + block.gpe_count as u16)
+            .max()
+            .unwrap_or(0)
+    }
+
+    // AcpiEvMatchGpeMethod
+    fn initialize_gpe_methods(
+        &mut self,
+        scope: &AmlName,
+        base_gpe_number: u16,
+    ) -> Result<(), AcpiSystemError> {
+        for (name, path) in self.scope_objects(scope)? {
+            let trigger = match name.get(..2) {
+                Some("_L") => GpeTrigger::Level,
+                Some("_E") => GpeTrigger::Edge,
+                _ => continue,
+            };
+            let Some(index) = name.get(2..4).and_then(|n| u16::from_str_radix(n, 16).ok()) else {
+                // Other _Exx/_Lxx names (e.g. _EJ0, _LCK) are not GPE methods
+                log::trace!("Not a GPE method: {}", path);
+                continue;
+            };
+            if !matches!(
+                self.aml_context.namespace.get_by_path(&path),
+                Ok(AmlValue::Method { .. })
+            ) {
+                continue;
+            }
+
+            let gpe_number = base_gpe_number + index;
+            let Some(info) = self
+                .gpe_block_mut(gpe_number)
+                .and_then(|block| block.event_info_mut(gpe_number))
+            else {
+                log::warn!(
+                    "{} refers to GPE #{:#x} outside of any GPE block",
+                    path,
+                    gpe_number
+                );
+                continue;
+            };
+
+            log::trace!("GPE #{:#x} method: {} ({:?})", gpe_number, path, trigger);
+            info.trigger = trigger;
+            info.method = Some(path);
+        }
+
+        Ok(())
+    }
+
+    // AcpiUpdateAllGpes: enable the runtime GPEs which have a method
+    pub(crate) fn enable_gpe_methods(&mut self) -> Result<(), AcpiSystemError> {
+        let mut gpes = vec![];
+        for block in self.gpe_blocks() {
+            for info in block.event_info.iter() {
+                if info.method.is_some() && info.gpe_type == GpeType::Runtime {
+                    gpes.push(info.gpe_number);
+                }
+            }
+        }
+
+        for gpe_number in gpes {
+            log::info!("Enable GPE #{:#x} (method)", gpe_number);
+            self.set_gpe_enabled(gpe_number, true)?;
         }
 
         Ok(())
@@ -229,11 +618,20 @@ impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
             {
                 log::trace!("Got event: {:?}", event.name);
 
-                event.status_register.set(self, true).ok();
+                // Clear the event by writing 1 into its status bit only, the other events are
+                // still pending
+                event.status_register.clear_status(self).ok();
 
-                // Clear the event by writing 1 into its status bit
-                if let Some(handler) = &self.event_handlers[event.handler_id] {
-                    self.handle_event_action(handler(self)).ok();
+                match event.handler_id {
+                    EventHandlerId::Timer => self.handle_pm_timer_overflow(),
+                    EventHandlerId::GlobalLock => self.handle_global_lock_event(),
+                    EventHandlerId::Rtc => self.handle_rtc_alarm(),
+                    _ => (),
+                }
+
+                if self.event_handlers.contains(&event.handler_id) {
+                    self.pending_events
+                        .push_back(PendingEvent::Fixed(event.handler_id));
                 }
             }
         }
@@ -241,6 +639,381 @@ impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
         Ok(())
     }
 
+    // Handles the GPE blocks routed to the interrupt, which is the SCI for FADT blocks
+    pub(crate) fn handle_gpe_interrupt(
+        &mut self,
+        interrupt_number: u32,
+    ) -> Result<(), AcpiSystemError> {
+        let mut pending = vec![];
+
+        // Collect the GPEs which are both enabled and active first, dispatching them may touch
+        // the registers
+        for block in self
+            .gpe_blocks()
+            .filter(|block| block.interrupt_number == interrupt_number)
+        {
+            for register in block.register_info.iter() {
+                let enabled = Self::read_address(register.enable_register)? as u8;
+                if enabled == 0 {
+                    continue;
+                }
+                let status = Self::read_address(register.status_register)? as u8;
+                let active = enabled & status;
+
+                for bit in 0..GPE_REGISTER_WIDTH {
+                    if active & (1 << bit) != 0 {
+                        pending.push(register.base_gpe_number + bit as u16);
+                    }
+                }
+            }
+        }
+
+        for gpe_number in pending {
+            if let Err(error) = self.acknowledge_gpe(gpe_number) {
+                log::warn!("GPE #{:#x}: {:?}", gpe_number, error);
+            }
+        }
+
+        Ok(())
+    }
+
+    // AcpiEvGpeDispatch
+    fn acknowledge_gpe(&mut self, gpe_number: u16) -> Result<(), AcpiSystemError> {
+        let info = self
+            .gpe_event_info(gpe_number)
+            .ok_or(AcpiSystemError::InvalidGpe(gpe_number))?;
+        let trigger = info.trigger;
+
+        log::trace!("Got GPE #{:#x} ({:?})", info.gpe_number, trigger);
+
+        // Keep the GPE from firing again until it's handled
+        self.mask_gpe(gpe_number, true)?;
+
+        if trigger == GpeTrigger::Edge {
+            self.clear_gpe(gpe_number)?;
+        }
+
+        self.pending_events.push_back(PendingEvent::Gpe(gpe_number));
+        Ok(())
+    }
+
+    // AcpiEvAsynchExecuteGpeMethod + AcpiEvFinishGpe
+    fn dispatch_gpe(&mut self, gpe_number: u16) -> Result<(), AcpiSystemError> {
+        let info = self
+            .gpe_event_info(gpe_number)
+            .ok_or(AcpiSystemError::InvalidGpe(gpe_number))?;
+        let trigger = info.trigger;
+        let method = info.method.clone();
+
+        let action = self.run_handler(
+            |system| &mut system.gpe_handlers,
+            &gpe_number,
+            |system, handler| handler(system),
+        );
+        let result = if let Some(action) = action {
+            self.handle_event_action(action)
+        } else if let Some(method) = method {
+            log::trace!("Run GPE method: {}", method);
+            self.aml_context
+                .invoke_method(&method, aml::value::Args::EMPTY)
+                .map(|_| ())
+                .map_err(AcpiSystemError::from)
+        } else {
+            // Nothing can handle this GPE, leave it disabled
+            log::warn!(
+                "No handler or method for GPE #{:#x}, disabling it",
+                gpe_number
+            );
+            return self.set_gpe_enabled(gpe_number, false);
+        };
+
+        // Even if the handler failed, the GPE must be cleared and unmasked, or it is never
+        // delivered again
+        if trigger == GpeTrigger::Level {
+            self.clear_gpe(gpe_number)?;
+        }
+
+        self.mask_gpe(gpe_number, false)?;
+        result
+    }
+
+    // Runs the handler installed in the table for the key, see HandlerTable
+    pub(crate) fn run_handler<K: PartialEq, F, R>(
+        &mut self,
+        table: fn(&mut Self) -> &mut HandlerTable<K, F>,
+        key: &K,
+        run: impl FnOnce(&mut Self, &mut F) -> R,
+    ) -> Option<R> {
+        let mut handler = table(self).take(key)?;
+        let result = run(self, &mut handler);
+        table(self).restore(key, handler);
+        Some(result)
+    }
+
+    pub(crate) fn run_event_handler(&mut self, handler_id: EventHandlerId) -> EventAction {
+        self.run_handler(
+            |system| &mut system.event_handlers,
+            &handler_id,
+            |system, handler| handler(system),
+        )
+        .unwrap_or(EventAction::Nothing)
+    }
+
+    fn dispatch_fixed_event(&mut self, handler_id: EventHandlerId) -> Result<(), AcpiSystemError> {
+        let action = self.run_event_handler(handler_id);
+        self.handle_event_action(action)
+    }
+
+    /// Runs the work queued by the interrupt handlers: fixed event and GPE handlers, GPE and GED
+    /// methods and notify handlers. Unlike the interrupt handlers, the event handlers may evaluate
+    /// AML, so the OS should call this from a thread, outside of the interrupt context.
+    pub fn process_pending_events(&mut self) {
+        while let Some(event) = self.pending_events.pop_front() {
+            let result = match &event {
+                PendingEvent::Fixed(handler_id) => self.dispatch_fixed_event(*handler_id),
+                PendingEvent::Gpe(gpe_number) => self.dispatch_gpe(*gpe_number),
+                PendingEvent::Ged { device, irq } => self.evaluate_ged_event(device, *irq),
+            };
+
+            if let Err(error) = result {
+                match event {
+                    PendingEvent::Fixed(handler_id) => log::warn!("{:?}: {:?}", handler_id, error),
+                    PendingEvent::Gpe(gpe_number) => {
+                        log::warn!("GPE #{:#x}: {:?}", gpe_number, error)
+                    }
+                    PendingEvent::Ged { device, irq } => {
+                        log::warn!("{} (IRQ {}): {:?}", device, irq, error)
+                    }
+                }
+            }
+
+            // Event methods usually notify the devices whose state has changed
+            self.process_notifications();
+        }
+
+        // AML evaluated outside of the event handling (e.g. _INI, _DSW) may notify as well
+        self.process_notifications();
+    }
+
+    /// Returns true if there are events queued for [AcpiSystem::process_pending_events]
+    pub fn has_pending_events(&self) -> bool {
+        !self.pending_events.is_empty()
+    }
+
+    // GPE management
+    pub(crate) fn gpe_blocks(&self) -> impl Iterator<Item = &GpeBlock> {
+        self.gpe0_block
+            .iter()
+            .chain(self.gpe1_block.iter())
+            .chain(self.gpe_device_blocks.iter())
+    }
+
+    fn gpe_block(&self, gpe_number: u16) -> Option<&GpeBlock> {
+        self.gpe_blocks().find(|block| block.contains(gpe_number))
+    }
+
+    fn gpe_block_mut(&mut self, gpe_number: u16) -> Option<&mut GpeBlock> {
+        self.gpe0_block
+            .iter_mut()
+            .chain(self.gpe1_block.iter_mut())
+            .chain(self.gpe_device_blocks.iter_mut())
+            .find(|block| block.contains(gpe_number))
+    }
+
+    fn gpe_event_info(&self, gpe_number: u16) -> Option<&GpeEventInfo> {
+        self.gpe_block(gpe_number)?.event_info(gpe_number)
+    }
+
+    pub(crate) fn set_gpe_trigger(
+        &mut self,
+        gpe_number: u16,
+        trigger: GpeTrigger,
+    ) -> Result<(), AcpiSystemError> {
+        let info = self
+            .gpe_block_mut(gpe_number)
+            .and_then(|block| block.event_info_mut(gpe_number))
+            .ok_or(AcpiSystemError::InvalidGpe(gpe_number))?;
+        info.trigger = trigger;
+        Ok(())
+    }
+
+    // Updates the GPE's bit in the enable mask matching its type. Only the runtime mask is written
+    // to the hardware, wake GPEs are enabled right before entering a sleep state.
+    pub(crate) fn set_gpe_enabled(
+        &mut self,
+        gpe_number: u16,
+        enabled: bool,
+    ) -> Result<(), AcpiSystemError> {
+        let block = self
+            .gpe_block_mut(gpe_number)
+            .ok_or(AcpiSystemError::InvalidGpe(gpe_number))?;
+        let (index, mask) = block.register_mask(gpe_number).unwrap();
+        let gpe_type = block.event_info(gpe_number).unwrap().gpe_type;
+        let register = &mut block.register_info[index];
+
+        let enable_mask = match gpe_type {
+            GpeType::Runtime => &mut register.enable_for_run,
+            GpeType::Wake => &mut register.enable_for_wake,
+        };
+
+        if enabled {
+            *enable_mask |= mask;
+        } else {
+            *enable_mask &= !mask;
+        }
+
+        Self::write_address(register.enable_register, register.run_enable_value() as u64)
+    }
+
+    // Sets the GPE's bit in the wake enable mask, which is only written to the hardware when
+    // entering a sleep state. The runtime enable state and the GPE type are left untouched, so
+    // GPEs used for both wake and runtime events keep being dispatched.
+    pub(crate) fn set_gpe_wake_enabled(
+        &mut self,
+        gpe_number: u16,
+        enabled: bool,
+    ) -> Result<(), AcpiSystemError> {
+        let block = self
+            .gpe_block_mut(gpe_number)
+            .ok_or(AcpiSystemError::InvalidGpe(gpe_number))?;
+        let (index, mask) = block.register_mask(gpe_number).unwrap();
+        let register = &mut block.register_info[index];
+
+        if enabled {
+            register.enable_for_wake |= mask;
+        } else {
+            register.enable_for_wake &= !mask;
+        }
+
+        Ok(())
+    }
+
+    /// Marks a general-purpose event as either a runtime or a wake one. The GPE keeps its enable
+    /// state.
+    pub fn set_gpe_type(
+        &mut self,
+        gpe_number: u16,
+        gpe_type: GpeType,
+    ) -> Result<(), AcpiSystemError> {
+        let info = self
+            .gpe_block(gpe_number)
+            .and_then(|block| block.event_info(gpe_number))
+            .ok_or(AcpiSystemError::InvalidGpe(gpe_number))?;
+
+        if info.gpe_type == gpe_type {
+            return Ok(());
+        }
+
+        // Move the enable bit over to the mask of the new type
+        let enabled = self.gpe_status(gpe_number)?.enabled;
+        self.set_gpe_enabled(gpe_number, false)?;
+
+        let block = self.gpe_block_mut(gpe_number).unwrap();
+        block.event_info_mut(gpe_number).unwrap().gpe_type = gpe_type;
+
+        self.set_gpe_enabled(gpe_number, enabled)
+    }
+
+    /// Returns the current state of a general-purpose event
+    pub fn gpe_status(&self, gpe_number: u16) -> Result<GpeStatus, AcpiSystemError> {
+        let block = self
+            .gpe_block(gpe_number)
+            .ok_or(AcpiSystemError::InvalidGpe(gpe_number))?;
+        let (index, mask) = block.register_mask(gpe_number).unwrap();
+        let info = block.event_info(gpe_number).unwrap();
+        let register = &block.register_info[index];
+
+        let enabled = match info.gpe_type {
+            GpeType::Runtime => register.enable_for_run & mask != 0,
+            GpeType::Wake => register.enable_for_wake & mask != 0,
+        };
+        let active = Self::read_address(register.status_register)? as u8 & mask != 0;
+        let has_handler = info.method.is_some() || self.gpe_handlers.contains(&gpe_number);
+
+        Ok(GpeStatus {
+            gpe_type: info.gpe_type,
+            trigger: info.trigger,
+            enabled,
+            active,
+            has_handler,
+        })
+    }
+
+    // Temporarily disables the GPE in hardware without touching its runtime enable state
+    fn mask_gpe(&mut self, gpe_number: u16, masked: bool) -> Result<(), AcpiSystemError> {
+        let block = self
+            .gpe_block_mut(gpe_number)
+            .ok_or(AcpiSystemError::InvalidGpe(gpe_number))?;
+        let (index, mask) = block.register_mask(gpe_number).unwrap();
+        let register = &mut block.register_info[index];
+
+        // Other GPEs of the register may be masked as well, so they must stay disabled
+        if masked {
+            register.masked |= mask;
+        } else {
+            register.masked &= !mask;
+        }
+
+        Self::write_address(register.enable_register, register.run_enable_value() as u64)
+    }
+
+    /// Clears the status bit of a general-purpose event
+    pub fn clear_gpe(&self, gpe_number: u16) -> Result<(), AcpiSystemError> {
+        let block = self
+            .gpe_block(gpe_number)
+            .ok_or(AcpiSystemError::InvalidGpe(gpe_number))?;
+        let (index, mask) = block.register_mask(gpe_number).unwrap();
+
+        // Status bits are cleared by writing 1 into them
+        Self::write_address(block.register_info[index].status_register, mask as u64)
+    }
+
+    // AcpiHwDisableAllGpes + AcpiHwEnableAllWakeupGpes: leave only the wake GPEs enabled and
+    // clear whatever is pending, so the system doesn't wake up right away
+    pub(crate) fn enable_wake_gpes(&self) -> Result<(), AcpiSystemError> {
+        log::trace!("Enable wake GPEs");
+        for block in self.gpe_blocks() {
+            for register in block.register_info.iter() {
+                Self::write_address(register.enable_register, 0x00)?;
+                Self::write_address(register.status_register, 0xFF)?;
+                Self::write_address(register.enable_register, register.enable_for_wake as u64)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Returns the wake-enabled GPEs with their status bit set
+    pub(crate) fn active_wake_gpes(&self) -> Result<Vec<u16>, AcpiSystemError> {
+        let mut gpes = vec![];
+        for block in self.gpe_blocks() {
+            for register in block.register_info.iter() {
+                if register.enable_for_wake == 0 {
+                    continue;
+                }
+                let status = Self::read_address(register.status_register)? as u8;
+                let active = status & register.enable_for_wake;
+
+                for bit in 0..GPE_REGISTER_WIDTH {
+                    if active & (1 << bit) != 0 {
+                        gpes.push(register.base_gpe_number + bit as u16);
+                    }
+                }
+            }
+        }
+        Ok(gpes)
+    }
+
+    // AcpiHwEnableAllRuntimeGpes
+    pub(crate) fn enable_runtime_gpes(&self) -> Result<(), AcpiSystemError> {
+        log::trace!("Enable runtime GPEs");
+        for block in self.gpe_blocks() {
+            for register in block.register_info.iter() {
+                Self::write_address(register.enable_register, register.run_enable_value() as u64)?;
+            }
+        }
+        Ok(())
+    }
+
     pub(crate) fn clear_fixed_events(&mut self) -> Result<(), AcpiSystemError> {
         log::trace!("Clear fixed events");
         let value = self.read_register(AcpiRegister::Pm1Status)?;
@@ -0,0 +1,93 @@
+use core::ptr::addr_of_mut;
+
+use acpi::AcpiHandler;
+
+use crate::{AcpiSystem, AcpiSystemError, Handler};
+
+// FACS.Flags
+const FACS_S4BIOS: u32 = 1 << 0;
+const FACS_64BIT_WAKE_SUPPORTED: u32 = 1 << 1;
+// FACS.OSPM_Flags
+const FACS_OSPM_64BIT_WAKE: u32 = 1 << 0;
+
+/// Firmware ACPI Control Structure
+#[allow(dead_code)]
+#[repr(C, packed)]
+pub(crate) struct Facs {
+    signature: [u8; 4],
+    length: u32,
+    hardware_signature: u32,
+    firmware_waking_vector: u32,
+    global_lock: u32,
+    flags: u32,
+    x_firmware_waking_vector: u64,
+    version: u8,
+    _reserved0: [u8; 3],
+    ospm_flags: u32,
+    _reserved1: [u8; 24],
+}
+
+/// Entry point the firmware transfers control to when the system resumes from a sleep state
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FirmwareWakingVector {
+    /// Real mode entry point (Firmware_Waking_Vector), the firmware jumps to it with
+    /// CS = address >> 4 and IP = address & 0xF
+    Legacy(u32),
+    /// 32-bit protected mode entry point (X_Firmware_Waking_Vector), the firmware jumps to it
+    /// with paging disabled
+    Extended(u64),
+    /// 64-bit entry point (X_Firmware_Waking_Vector), only usable if the firmware reports 64-bit
+    /// wake support in the FACS
+    Extended64(u64),
+}
+
+impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
+    pub(crate) fn facs_ptr(&self) -> Result<*mut Facs, AcpiSystemError> {
+        self.facs
+            .as_ref()
+            .map(|facs| facs.virtual_start().as_ptr())
+            .ok_or(AcpiSystemError::MissingFacs)
+    }
+
+    /// Returns true if the firmware is able to save and restore the memory image for S4 itself
+    pub fn s4bios_supported(&self) -> bool {
+        let Ok(facs) = self.facs_ptr() else {
+            return false;
+        };
+        let flags = unsafe { (*facs).flags };
+
+        flags & FACS_S4BIOS != 0 && self.fadt.s4bios_req != 0
+    }
+
+    // AcpiHwSetFirmwareWakingVector
+    pub(crate) fn set_firmware_waking_vector(
+        &mut self,
+        vector: FirmwareWakingVector,
+    ) -> Result<(), AcpiSystemError> {
+        log::trace!("Set firmware waking vector: {:#x?}", vector);
+        let facs = self.facs_ptr()?;
+
+        // The X_ fields are only present in FACS version 1 and above
+        let (version, flags) = unsafe { ((*facs).version, (*facs).flags) };
+        let (legacy, extended, ospm_flags) = match vector {
+            FirmwareWakingVector::Legacy(address) => (address, 0, 0),
+            FirmwareWakingVector::Extended(address) if version >= 1 => (0, address, 0),
+            FirmwareWakingVector::Extended64(address)
+                if version >= 1 && flags & FACS_64BIT_WAKE_SUPPORTED != 0 =>
+            {
+                (0, address, FACS_OSPM_64BIT_WAKE)
+            }
+            _ => return Err(AcpiSystemError::UnsupportedWakingVector(vector)),
+        };
+
+        unsafe {
+            addr_of_mut!((*facs).firmware_waking_vector).write_volatile(legacy);
+            if version >= 1 {
+                addr_of_mut!((*facs).x_firmware_waking_vector).write_volatile(extended);
+                addr_of_mut!((*facs).ospm_flags).write_volatile(ospm_flags);
+            }
+        }
+
+        Ok(())
+    }
+}
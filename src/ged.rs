@@ -0,0 +1,107 @@
+use acpi::AcpiHandler;
+use alloc::{vec, vec::Vec};
+use aml::{
+    resource::{self, Resource},
+    value::Args,
+    AmlName, AmlValue,
+};
+
+use crate::{event::PendingEvent, AcpiSystem, AcpiSystemError, Handler};
+
+const HID_GENERIC_EVENT_DEVICE: &str = "ACPI0013";
+// Event method, arg0 - the number of the interrupt which has fired
+const NAME_EVENT: &str = "_EVT";
+
+// An interrupt listed in the _CRS of a Generic Event Device
+pub(crate) struct GedInterrupt {
+    device: AmlName,
+    interrupt_number: u32,
+}
+
+impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
+    // Generic Event Devices replace the SCI + GPE pair on hardware-reduced platforms: each of the
+    // interrupts in their _CRS is handled by evaluating their _EVT method
+    pub(crate) fn initialize_ged_devices(&mut self) -> Result<(), AcpiSystemError> {
+        for device in self.find_devices(HID_GENERIC_EVENT_DEVICE)? {
+            if let Err(error) = self.initialize_ged_device(&device) {
+                log::warn!("{}: {:?}", device, error);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn initialize_ged_device(&mut self, device: &AmlName) -> Result<(), AcpiSystemError> {
+        let path = AmlName::from_str("_CRS").unwrap().resolve(device)?;
+        let crs = self
+            .evaluate_object(&path)?
+            .ok_or(AcpiSystemError::InvalidGedDevice)?;
+
+        let interrupts = resource::resource_descriptor_list(&crs)?
+            .into_iter()
+            .filter_map(|resource| match resource {
+                Resource::Irq(irq) => Some(irq.irq),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        if interrupts.is_empty() {
+            return Err(AcpiSystemError::InvalidGedDevice);
+        }
+
+        for interrupt_number in interrupts {
+            log::info!("{}: generic event device, IRQ {}", device, interrupt_number);
+            H::install_interrupt_handler(interrupt_number)?;
+            self.ged_interrupts.push(GedInterrupt {
+                device: device.clone(),
+                interrupt_number,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the interrupts of the Generic Event Devices, which are passed to
+    /// [Handler::install_interrupt_handler] during initialization. Each of them must be reported
+    /// through [AcpiSystem::handle_ged_interrupt].
+    pub fn ged_interrupts(&self) -> impl Iterator<Item = u32> + '_ {
+        self.ged_interrupts
+            .iter()
+            .map(|interrupt| interrupt.interrupt_number)
+    }
+
+    /// Handles an interrupt of a Generic Event Device. Its _EVT method is evaluated by
+    /// [AcpiSystem::process_pending_events].
+    pub fn handle_ged_interrupt(&mut self, irq: u32) {
+        let devices = self
+            .ged_interrupts
+            .iter()
+            .filter(|interrupt| interrupt.interrupt_number == irq)
+            .map(|interrupt| interrupt.device.clone())
+            .collect::<Vec<_>>();
+
+        if devices.is_empty() {
+            log::warn!("Unexpected GED interrupt: IRQ {}", irq);
+        }
+
+        for device in devices {
+            self.pending_events
+                .push_back(PendingEvent::Ged { device, irq });
+        }
+    }
+
+    pub(crate) fn evaluate_ged_event(
+        &mut self,
+        device: &AmlName,
+        irq: u32,
+    ) -> Result<(), AcpiSystemError> {
+        let path = AmlName::from_str(NAME_EVENT).unwrap().resolve(device)?;
+        let args = Args::from_list(vec![AmlValue::Integer(irq as u64)]).unwrap();
+
+        if !self.invoke_optional_method(&path, args)? {
+            return Err(AcpiSystemError::InvalidGedDevice);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use core::{
+    ptr::addr_of_mut,
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use acpi::AcpiHandler;
+
+use crate::{hardware::AcpiBitRegister, AcpiSystem, AcpiSystemError, FixedEvent, Handler};
+
+// FACS.Global_Lock
+const GLOBAL_LOCK_PENDING: u32 = 1 << 0;
+const GLOBAL_LOCK_OWNED: u32 = 1 << 1;
+
+// How often GBL_STS is checked while waiting for the firmware to release the lock
+const GLOBAL_LOCK_POLL_INTERVAL: Duration = Duration::from_micros(100);
+
+impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
+    fn global_lock(&self) -> Result<&AtomicU32, AcpiSystemError> {
+        let facs = self.facs_ptr()?;
+
+        // The FACS is 64-byte aligned, so the lock word is naturally aligned
+        Ok(unsafe { AtomicU32::from_ptr(addr_of_mut!((*facs).global_lock)) })
+    }
+
+    // Sets the owned bit, or the pending one if the firmware owns the lock. Returns true if the
+    // lock has been acquired.
+    fn try_acquire_global_lock(&self) -> Result<bool, AcpiSystemError> {
+        let lock = self.global_lock()?;
+
+        let value = lock
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |old| {
+                let mut new = (old & !GLOBAL_LOCK_PENDING) | GLOBAL_LOCK_OWNED;
+                if old & GLOBAL_LOCK_OWNED != 0 {
+                    new |= GLOBAL_LOCK_PENDING;
+                }
+                Some(new)
+            })
+            .unwrap();
+
+        Ok(value & GLOBAL_LOCK_OWNED == 0)
+    }
+
+    /// Acquires the FACS Global Lock shared with the firmware, waiting up to `timeout` for the
+    /// firmware to release it. The lock may be acquired recursively, each acquisition has to be
+    /// balanced by [AcpiSystem::release_global_lock].
+    pub fn acquire_global_lock(&mut self, timeout: Duration) -> Result<(), AcpiSystemError> {
+        // There's no GBL_STS/GBL_RLS to synchronize with the firmware through
+        if self.is_hardware_reduced() {
+            return Err(AcpiSystemError::NotSupportedOnReducedHardware);
+        }
+
+        if self.global_lock_depth != 0 {
+            self.global_lock_depth += 1;
+            return Ok(());
+        }
+
+        if !self.try_acquire_global_lock()? {
+            // The pending bit is set now, so the firmware will signal GBL_STS once it releases
+            // the lock
+            let mut remaining = timeout;
+            loop {
+                if self.global_lock_signaled || FixedEvent::GLOBAL_LOCK.status_register.get(self)? {
+                    self.global_lock_signaled = false;
+                    FixedEvent::GLOBAL_LOCK.status_register.clear_status(self)?;
+
+                    if self.try_acquire_global_lock()? {
+                        break;
+                    }
+                }
+
+                if remaining.is_zero() {
+                    return Err(AcpiSystemError::GlobalLockTimeout);
+                }
+
+                H::stall(GLOBAL_LOCK_POLL_INTERVAL);
+                remaining = remaining.saturating_sub(GLOBAL_LOCK_POLL_INTERVAL);
+            }
+        }
+
+        log::trace!("Acquired the global lock");
+        self.global_lock_depth = 1;
+        Ok(())
+    }
+
+    /// Releases the FACS Global Lock, signaling GBL_RLS if the firmware is waiting for it
+    pub fn release_global_lock(&mut self) -> Result<(), AcpiSystemError> {
+        match self.global_lock_depth {
+            0 => return Err(AcpiSystemError::GlobalLockNotAcquired),
+            1 => (),
+            _ => {
+                self.global_lock_depth -= 1;
+                return Ok(());
+            }
+        }
+
+        let lock = self.global_lock()?;
+        let value = lock
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |old| {
+                Some(old & !(GLOBAL_LOCK_PENDING | GLOBAL_LOCK_OWNED))
+            })
+            .unwrap();
+
+        self.global_lock_depth = 0;
+        log::trace!("Released the global lock");
+
+        if value & GLOBAL_LOCK_PENDING != 0 {
+            AcpiBitRegister::GLOBAL_LOCK_RELEASE.set(self, true)?;
+        }
+
+        Ok(())
+    }
+
+    // Called from the SCI handler on GBL_STS, the firmware has released the lock we're waiting for
+    pub(crate) fn handle_global_lock_event(&mut self) {
+        self.global_lock_signaled = true;
+    }
+}
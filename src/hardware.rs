@@ -2,6 +2,7 @@ use core::{ops::Range, time::Duration};
 
 use acpi::{
     address::{AccessSize, AddressSpace, GenericAddress},
+    fadt::Pm1Registers,
     AcpiHandler,
 };
 use bit_field::BitField;
@@ -31,6 +32,10 @@ impl AcpiBitRegister {
         parent: AcpiRegister::Pm1Control,
         position: 0,
     };
+    pub(crate) const GLOBAL_LOCK_RELEASE: Self = Self {
+        parent: AcpiRegister::Pm1Control,
+        position: 2,
+    };
     pub(crate) const SLEEP_ENABLE: Self = Self {
         parent: AcpiRegister::Pm1Control,
         position: 13,
@@ -39,6 +44,10 @@ impl AcpiBitRegister {
         parent: AcpiRegister::Pm1Status,
         position: 15,
     };
+    pub(crate) const PCIEXP_WAKE_STATUS: Self = Self {
+        parent: AcpiRegister::Pm1Status,
+        position: 14,
+    };
 
     pub(crate) const fn new(parent: AcpiRegister, position: usize) -> Self {
         Self { parent, position }
@@ -68,6 +77,15 @@ impl AcpiBitRegister {
         Ok(reg_value.get_bit(self.position))
     }
 
+    // Clears a write-1-to-clear status bit. Unlike set(), the other status bits are written as
+    // 0, so the events which are still pending don't get cleared along with it.
+    pub fn clear_status<'a, H: Handler + AcpiHandler + 'a>(
+        &self,
+        context: &mut AcpiSystem<'a, H>,
+    ) -> Result<(), AcpiSystemError> {
+        context.write_register(self.parent, self.set_raw(0, true))
+    }
+
     #[inline]
     pub fn get_from_raw(&self, value: u32) -> bool {
         value.get_bit(self.position)
@@ -124,45 +142,76 @@ fn access_bit_width(register: &GenericAddress, address: u64, mut maximum_width:
     core::cmp::min(access_bit_width, maximum_width)
 }
 
+// Splits a PCI configuration space GAS address into device, function and register offset. Such
+// registers are always located in segment 0, bus 0.
+fn pci_config_address(address: u64) -> (u8, u8, u16) {
+    let device = address.get_bits(32..48) as u8;
+    let function = address.get_bits(16..32) as u8;
+    let offset = address.get_bits(0..16) as u16;
+
+    (device, function, offset)
+}
+
 impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
+    /// Returns true if the platform implements the hardware-reduced ACPI model, i.e. has no PM1x,
+    /// GPE and SMI_CMD fixed hardware
+    pub fn is_hardware_reduced(&self) -> bool {
+        let flags = self.fadt.flags;
+        flags.is_hw_reduced_acpi()
+    }
+
+    fn pm1_registers(&self) -> Result<&Pm1Registers, AcpiSystemError> {
+        self.pm1_registers
+            .as_ref()
+            .ok_or(AcpiSystemError::NotSupportedOnReducedHardware)
+    }
+
     pub(crate) fn write_register(
         &mut self,
         register: AcpiRegister,
         value: u32,
     ) -> Result<(), AcpiSystemError> {
+        let pm1_registers = self.pm1_registers()?;
+
         match register {
             AcpiRegister::Pm1Status => {
                 let value = value & !PM1_STATUS_PRESERVED_BITS;
 
-                let pm1a = self.pm1_registers.x_pm1a_status;
-                let pm1b = self.pm1_registers.x_pm1b_status;
+                let pm1a = pm1_registers.x_pm1a_status;
+                let pm1b = pm1_registers.x_pm1b_status;
 
                 Self::write_register_pair(pm1a, pm1b, value)
             }
             AcpiRegister::Pm1Enable => {
-                let pm1a = self.pm1_registers.x_pm1a_enable;
-                let pm1b = self.pm1_registers.x_pm1b_enable;
+                let pm1a = pm1_registers.x_pm1a_enable;
+                let pm1b = pm1_registers.x_pm1b_enable;
 
                 Self::write_register_pair(pm1a, pm1b, value)
             }
             AcpiRegister::Pm1Control => {
-                // Pm1Control registers are hanled differently
-                unimplemented!()
+                // Same value into both registers, like ACPICA does. SLP_TYP has to be written
+                // through write_pm1_control instead.
+                let pm1a = self.fadt.pm1a_control_block()?;
+                let pm1b = self.fadt.pm1b_control_block()?;
+
+                Self::write_register_pair(pm1a, pm1b, value)
             }
         }
     }
 
     pub(crate) fn read_register(&self, register: AcpiRegister) -> Result<u32, AcpiSystemError> {
+        let pm1_registers = self.pm1_registers()?;
+
         match register {
             AcpiRegister::Pm1Status => {
-                let pm1a = self.pm1_registers.x_pm1a_status;
-                let pm1b = self.pm1_registers.x_pm1b_status;
+                let pm1a = pm1_registers.x_pm1a_status;
+                let pm1b = pm1_registers.x_pm1b_status;
 
                 Self::read_register_pair(pm1a, pm1b)
             }
             AcpiRegister::Pm1Enable => {
-                let pm1a = self.pm1_registers.x_pm1a_enable;
-                let pm1b = self.pm1_registers.x_pm1b_enable;
+                let pm1a = pm1_registers.x_pm1a_enable;
+                let pm1b = pm1_registers.x_pm1b_enable;
 
                 Self::read_register_pair(pm1a, pm1b)
             }
@@ -182,6 +231,8 @@ impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
         reg_a_value: u32,
         reg_b_value: u32,
     ) -> Result<(), AcpiSystemError> {
+        self.pm1_registers()?;
+
         let pm1a = self.fadt.pm1a_control_block()?;
         let pm1b = self.fadt.pm1b_control_block()?;
 
@@ -236,16 +287,26 @@ impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
 
                 match width {
                     8 => Ok(H::io_read_u8(address) as _),
-                    16 => Ok(H::io_read_u32(address) as _),
-                    32 => Ok(H::io_read_u16(address) as _),
+                    16 => Ok(H::io_read_u16(address) as _),
+                    32 => Ok(H::io_read_u32(address) as _),
                     _ => unimplemented!(),
                 }
             }
-            _ => unimplemented!(),
+            AddressSpace::PciConfigSpace => {
+                let (device, function, offset) = pci_config_address(address);
+
+                match width {
+                    8 => Ok(H::pci_read_u8(0, 0, device, function, offset)? as _),
+                    16 => Ok(H::pci_read_u16(0, 0, device, function, offset)? as _),
+                    32 => Ok(H::pci_read_u32(0, 0, device, function, offset)? as _),
+                    _ => unimplemented!(),
+                }
+            }
+            _ => Err(AcpiSystemError::UnsupportedAddressSpace(space)),
         }
     }
 
-    fn write_address_space(
+    pub(crate) fn write_address_space(
         space: AddressSpace,
         address: u64,
         width: usize,
@@ -253,7 +314,15 @@ impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
     ) -> Result<(), AcpiSystemError> {
         match space {
             AddressSpace::SystemMemory => {
-                todo!()
+                match width {
+                    8 => H::mem_write_u8(address, value as u8),
+                    16 => H::mem_write_u16(address, value as u16),
+                    32 => H::mem_write_u32(address, value as u32),
+                    64 => H::mem_write_u64(address, value),
+                    _ => unimplemented!(),
+                };
+
+                Ok(())
             }
             AddressSpace::SystemIo => {
                 let address = address.try_into().unwrap();
@@ -267,7 +336,17 @@ impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
 
                 Ok(())
             }
-            _ => unimplemented!(),
+            AddressSpace::PciConfigSpace => {
+                let (device, function, offset) = pci_config_address(address);
+
+                match width {
+                    8 => H::pci_write_u8(0, 0, device, function, offset, value as u8),
+                    16 => H::pci_write_u16(0, 0, device, function, offset, value as u16),
+                    32 => H::pci_write_u32(0, 0, device, function, offset, value as u32),
+                    _ => unimplemented!(),
+                }
+            }
+            _ => Err(AcpiSystemError::UnsupportedAddressSpace(space)),
         }
     }
 
@@ -370,6 +449,50 @@ impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
         Err(AcpiSystemError::EnableTimeout)
     }
 
+    /// Resets the system by writing RESET_VALUE to the FADT RESET_REG. Returns
+    /// [AcpiSystemError::ResetNotSupported] if the platform doesn't implement it, in which case the
+    /// OS has to fall back to another reset method. The reset may not be immediate, so the OS
+    /// should do the same if the system is still running after a while.
+    pub fn reset(&mut self) -> Result<(), AcpiSystemError> {
+        let flags = self.fadt.flags;
+        if !flags.supports_system_reset_via_fadt() {
+            return Err(AcpiSystemError::ResetNotSupported);
+        }
+
+        let reset_register = self.fadt.reset_register()?;
+        if reset_register.address == 0 {
+            return Err(AcpiSystemError::ResetNotSupported);
+        }
+
+        // Only these address spaces are allowed for RESET_REG
+        if !matches!(
+            reset_register.address_space,
+            AddressSpace::SystemIo | AddressSpace::SystemMemory | AddressSpace::PciConfigSpace
+        ) {
+            log::error!(
+                "Unsupported reset register address space: {:?}",
+                reset_register.address_space
+            );
+            return Err(AcpiSystemError::ResetNotSupported);
+        }
+
+        log::info!("Resetting the system");
+        let reset_value = self.fadt.reset_value;
+        // RESET_REG is always an 8-bit register, regardless of the bit width firmware reports
+        // (which is sometimes 0)
+        Self::write_address_space(
+            reset_register.address_space,
+            reset_register.address,
+            8,
+            reset_value as u64,
+        )
+        .map_err(|error| match error {
+            // The OS doesn't provide PCI configuration space access
+            AcpiSystemError::PciConfigNotSupported => AcpiSystemError::ResetNotSupported,
+            error => error,
+        })
+    }
+
     pub(crate) fn is_acpi_enabled(&mut self) -> Result<bool, AcpiSystemError> {
         if self.fadt.smi_cmd_port == 0 {
             return Ok(true);
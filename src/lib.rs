@@ -3,26 +3,48 @@
 
 extern crate alloc;
 
-use core::{ops::Deref, time::Duration};
+use core::{mem::size_of, ops::Deref, time::Duration};
 
 use acpi::{
     fadt::{Fadt, Pm1Registers},
     AcpiHandler, AcpiTables, PhysicalMapping,
 };
-use alloc::{boxed::Box, vec};
+use alloc::{boxed::Box, collections::VecDeque, vec, vec::Vec};
 use aml::{pci_routing::PciRoutingTable, AmlContext, AmlError, AmlName, AmlValue};
-use enum_map::EnumMap;
 
-use event::{EventHandlerId, GpeBlock};
+use event::{EventHandlerId, GpeBlock, HandlerTable, PendingEvent};
+use facs::Facs;
+use ged::GedInterrupt;
+use notify::{GlobalNotifyHandler, NotifyForwarder, NotifyHandler, NotifyQueue};
+use wake::WakeDevice;
 
+mod button;
+mod device;
 mod error;
 mod event;
+mod facs;
+mod ged;
+mod global_lock;
 mod hardware;
+mod namespace;
+mod notify;
+mod osc;
+mod pci;
+mod rtc;
 mod sleep;
+mod timer;
+mod wake;
 
+pub use device::{AcpiDevice, DeviceInitReport, DeviceStatus};
 pub use error::AcpiSystemError;
-pub use event::{EventAction, FixedEvent};
-pub use sleep::AcpiSleepState;
+pub use event::{EventAction, FixedEvent, GpeStatus, GpeTrigger, GpeType};
+pub use facs::FirmwareWakingVector;
+pub use osc::{PciOscControl, PciOscSupport, PlatformOscCapabilities};
+pub use pci::{PciRootBridge, PciWindow};
+pub use rtc::RtcAlarm;
+pub use sleep::{AcpiSleepState, HibernateMode};
+pub use timer::{pm_timer_ticks_to_duration, PM_TIMER_FREQUENCY};
+pub use wake::{WakeReason, WakeSource};
 
 // Re-export other ACPI types
 pub use aml::{
@@ -65,8 +87,84 @@ pub trait Handler: Clone + AcpiHandler {
     fn mem_write_u32(address: u64, value: u32);
     fn mem_write_u64(address: u64, value: u64);
 
+    /// PCI configuration space accessors, only needed if the FADT RESET_REG is located in the
+    /// PCI configuration space
+    fn pci_read_u8(
+        _segment: u16,
+        _bus: u8,
+        _device: u8,
+        _function: u8,
+        _offset: u16,
+    ) -> Result<u8, AcpiSystemError> {
+        Err(AcpiSystemError::PciConfigNotSupported)
+    }
+    fn pci_read_u16(
+        _segment: u16,
+        _bus: u8,
+        _device: u8,
+        _function: u8,
+        _offset: u16,
+    ) -> Result<u16, AcpiSystemError> {
+        Err(AcpiSystemError::PciConfigNotSupported)
+    }
+    fn pci_read_u32(
+        _segment: u16,
+        _bus: u8,
+        _device: u8,
+        _function: u8,
+        _offset: u16,
+    ) -> Result<u32, AcpiSystemError> {
+        Err(AcpiSystemError::PciConfigNotSupported)
+    }
+
+    fn pci_write_u8(
+        _segment: u16,
+        _bus: u8,
+        _device: u8,
+        _function: u8,
+        _offset: u16,
+        _value: u8,
+    ) -> Result<(), AcpiSystemError> {
+        Err(AcpiSystemError::PciConfigNotSupported)
+    }
+    fn pci_write_u16(
+        _segment: u16,
+        _bus: u8,
+        _device: u8,
+        _function: u8,
+        _offset: u16,
+        _value: u16,
+    ) -> Result<(), AcpiSystemError> {
+        Err(AcpiSystemError::PciConfigNotSupported)
+    }
+    fn pci_write_u32(
+        _segment: u16,
+        _bus: u8,
+        _device: u8,
+        _function: u8,
+        _offset: u16,
+        _value: u32,
+    ) -> Result<(), AcpiSystemError> {
+        Err(AcpiSystemError::PciConfigNotSupported)
+    }
+
     fn stall(duration: Duration);
 
+    /// Returns the entry point the firmware jumps to when the system resumes from S3. `None`
+    /// means the OS doesn't support resuming.
+    fn resume_trampoline() -> Option<FirmwareWakingVector> {
+        None
+    }
+
+    /// Saves the CPU context and calls `enter_sleep`, which puts the platform to sleep. When the
+    /// system wakes up, the firmware jumps to [Handler::resume_trampoline], which must restore
+    /// the saved context, so that this function returns as if `enter_sleep` did.
+    unsafe fn suspend(
+        _enter_sleep: &mut dyn FnMut() -> Result<(), AcpiSystemError>,
+    ) -> Result<(), AcpiSystemError> {
+        Err(AcpiSystemError::ResumeNotSupported)
+    }
+
     unsafe fn flush_cpu_cache() {
         #[cfg(target_arch = "x86_64")]
         {
@@ -98,13 +196,41 @@ pub struct AcpiSystem<'a, H: Handler + 'a> {
 
     // FADT and its PM1x registers
     fadt: PhysicalMapping<H, Fadt>,
-    pm1_registers: Pm1Registers,
+    pm1_registers: Option<Pm1Registers>,
+    facs: Option<PhysicalMapping<H, Facs>>,
 
     // Event handling
     gpe0_block: Option<GpeBlock>,
-    #[allow(dead_code)]
     gpe1_block: Option<GpeBlock>,
-    event_handlers: EnumMap<EventHandlerId, Option<Box<dyn Fn(&Self) -> EventAction>>>,
+    gpe_device_blocks: Vec<GpeBlock>,
+    event_handlers: HandlerTable<EventHandlerId, Box<dyn FnMut(&mut Self) -> EventAction>>,
+    gpe_handlers: HandlerTable<u16, Box<dyn FnMut(&mut Self) -> EventAction>>,
+    ged_interrupts: Vec<GedInterrupt>,
+    // Work deferred from the interrupt handlers
+    pending_events: VecDeque<PendingEvent>,
+    // EventAction::Custom tokens not yet polled by the OS
+    custom_events: VecDeque<u32>,
+
+    // Notify() operations and their handlers
+    notify_queue: NotifyQueue,
+    notify_handlers: HandlerTable<AmlName, NotifyHandler<Self>>,
+    global_notify_handler: HandlerTable<(), GlobalNotifyHandler<Self>>,
+
+    // Platform-wide _OSC capabilities advertised by the OS and acknowledged by the firmware
+    platform_osc_support: PlatformOscCapabilities,
+    platform_osc_granted: PlatformOscCapabilities,
+    // Outcome of the _STA/_INI pass
+    device_init_report: DeviceInitReport,
+
+    // Devices allowed to wake the system up
+    wake_devices: Vec<WakeDevice>,
+    // PM1x_EN value to restore when leaving a sleep state
+    saved_pm1_enable: u32,
+    // Number of times the PM timer MSB has changed, counted through TMR_STS
+    pm_timer_half_periods: u64,
+    // Global lock recursion depth and whether GBL_STS was signaled while waiting for it
+    global_lock_depth: u32,
+    global_lock_signaled: bool,
 }
 
 impl<'a, H: Handler + 'a> AcpiSystem<'a, H> {
@@ -113,8 +239,29 @@ impl<'a, H: Handler + 'a> AcpiSystem<'a, H> {
         aml_handler: Box<dyn aml::Handler>,
     ) -> Result<Self, AcpiSystemError> {
         let fadt = tables.find_table::<Fadt>()?;
-        let pm1_registers = fadt.pm1_registers()?;
+        // Hardware-reduced platforms don't implement the PM1x register blocks
+        let flags = fadt.flags;
+        let pm1_registers = if flags.is_hw_reduced_acpi() {
+            log::info!("Hardware-reduced ACPI platform");
+            None
+        } else {
+            Some(fadt.pm1_registers()?)
+        };
+        let facs = match fadt.facs_address() {
+            Ok(address) => Some(unsafe {
+                fadt.handler()
+                    .map_physical_region::<Facs>(address, size_of::<Facs>())
+            }),
+            Err(_) => {
+                log::warn!("No FACS present");
+                None
+            }
+        };
 
+        // Notify() operations are caught on their way to the OS's handler and queued for the
+        // notify handlers
+        let notify_queue = NotifyQueue::new();
+        let aml_handler = Box::new(NotifyForwarder::new(aml_handler, notify_queue.clone()));
         let aml_context = AmlContext::new(aml_handler, aml::DebugVerbosity::None);
 
         Ok(Self {
@@ -122,9 +269,26 @@ impl<'a, H: Handler + 'a> AcpiSystem<'a, H> {
             aml_context,
             fadt,
             pm1_registers,
+            facs,
             gpe0_block: None,
             gpe1_block: None,
-            event_handlers: EnumMap::default(),
+            gpe_device_blocks: Vec::new(),
+            event_handlers: HandlerTable::new(),
+            gpe_handlers: HandlerTable::new(),
+            ged_interrupts: Vec::new(),
+            pending_events: VecDeque::new(),
+            custom_events: VecDeque::new(),
+            notify_queue,
+            notify_handlers: HandlerTable::new(),
+            global_notify_handler: HandlerTable::new(),
+            platform_osc_support: PlatformOscCapabilities::BUILTIN,
+            platform_osc_granted: PlatformOscCapabilities::default(),
+            device_init_report: DeviceInitReport::default(),
+            wake_devices: Vec::new(),
+            saved_pm1_enable: 0,
+            pm_timer_half_periods: 0,
+            global_lock_depth: 0,
+            global_lock_signaled: false,
         })
     }
 
@@ -163,9 +327,38 @@ impl<'a, H: Handler + 'a> AcpiSystem<'a, H> {
                 .unwrap()
         }
 
-        self.initialize_events()?;
+        // Hardware-reduced platforms have neither fixed events nor GPEs, their events are
+        // signaled through GED/GPIO interrupts instead
+        let hardware_reduced = self.is_hardware_reduced();
 
-        self.aml_context.initialize_objects()?;
+        if !hardware_reduced {
+            self.initialize_events()?;
+        }
+
+        // Firmware may change what the _INI methods do depending on the OS capabilities
+        if let Err(error) = self.negotiate_platform_osc() {
+            log::warn!("Platform _OSC: {:?}", error);
+        }
+
+        let report = self.initialize_devices()?;
+        log::info!(
+            "Initialized {} devices, {} absent, {} failed",
+            report.initialized,
+            report.absent.len(),
+            report.failures.len()
+        );
+        self.device_init_report = report;
+
+        // GPE block devices are described by the namespace, so they can only be set up once it's
+        // initialized. Same goes for running the GPE methods.
+        if !hardware_reduced {
+            self.initialize_gpe_block_devices()?;
+            self.enable_gpe_methods()?;
+        }
+
+        // Generic Event Devices are mostly found on hardware-reduced platforms, but nothing
+        // prevents the others from having them
+        self.initialize_ged_devices()?;
 
         self.configure_aml_interrupt_method(interrupt_method)?;
 
@@ -173,6 +366,11 @@ impl<'a, H: Handler + 'a> AcpiSystem<'a, H> {
     }
 
     pub fn enable_acpi(&mut self) -> Result<(), AcpiSystemError> {
+        // There's no legacy mode to switch from on hardware-reduced platforms
+        if self.is_hardware_reduced() {
+            return Ok(());
+        }
+
         let state = self.is_acpi_enabled()?;
         log::trace!("Current ACPI status: {:?}", state);
 
@@ -186,14 +384,49 @@ impl<'a, H: Handler + 'a> AcpiSystem<'a, H> {
     pub fn enable_fixed_event(
         &mut self,
         event: &FixedEvent,
-        handler: Box<dyn Fn(&Self) -> EventAction>,
+        handler: Box<dyn FnMut(&mut Self) -> EventAction>,
     ) -> Result<(), AcpiSystemError> {
         log::info!("Enable ACPI event: {}", event.name);
-        self.event_handlers[event.handler_id].replace(handler);
+        self.event_handlers.install(event.handler_id, handler);
         event.enable_register.set(self, true)
     }
 
-    // TODO simplify OS's life by giving an iterator over the PCI root bridges
+    /// Enables a general-purpose event according to its [GpeType]. If a handler is given, it
+    /// replaces the one installed previously and takes precedence over the GPE's _Lxx/_Exx method.
+    pub fn enable_gpe(
+        &mut self,
+        gpe_number: u16,
+        handler: Option<Box<dyn FnMut(&mut Self) -> EventAction>>,
+    ) -> Result<(), AcpiSystemError> {
+        log::info!("Enable GPE #{:#x}", gpe_number);
+        if let Some(handler) = handler {
+            // Make sure the GPE exists before accepting the handler
+            self.gpe_status(gpe_number)?;
+            self.gpe_handlers.install(gpe_number, handler);
+        }
+        self.set_gpe_enabled(gpe_number, true)
+    }
+
+    /// Disables a general-purpose event. Its handler, if any, is kept installed.
+    pub fn disable_gpe(&mut self, gpe_number: u16) -> Result<(), AcpiSystemError> {
+        log::info!("Disable GPE #{:#x}", gpe_number);
+        self.set_gpe_enabled(gpe_number, false)
+    }
+
+    /// Installs a handler for a general-purpose event with the given trigger type and enables
+    /// the GPE
+    pub fn install_gpe_handler(
+        &mut self,
+        gpe_number: u16,
+        trigger: GpeTrigger,
+        handler: Box<dyn FnMut(&mut Self) -> EventAction>,
+    ) -> Result<(), AcpiSystemError> {
+        self.set_gpe_trigger(gpe_number, trigger)?;
+        self.enable_gpe(gpe_number, Some(handler))
+    }
+
+    /// Returns the IRQ a PCI device's interrupt pin is routed to, according to the _PRT of the
+    /// bridge at `aml_path`, see [AcpiSystem::pci_root_bridges]
     pub fn pci_route(
         &mut self,
         aml_path: &str,
@@ -211,20 +444,45 @@ impl<'a, H: Handler + 'a> AcpiSystem<'a, H> {
             })
     }
 
+    /// Acknowledges the fixed events and GPEs signaled through the SCI. Their handlers and
+    /// methods are run later by [AcpiSystem::process_pending_events].
     pub fn handle_sci(&mut self) {
         if let Err(err) = self.handle_fixed_event_sci() {
             log::warn!("{:?}", err);
         }
-        // TODO handle GPEs
+        if let Err(err) = self.handle_gpe_interrupt(self.fadt.sci_interrupt as u32) {
+            log::warn!("{:?}", err);
+        }
+    }
+
+    /// Handles an interrupt of a GPE block device which is not routed to the SCI, the same way
+    /// as [AcpiSystem::handle_sci] does
+    pub fn handle_gpe_block_interrupt(&mut self, irq: u32) {
+        if let Err(err) = self.handle_gpe_interrupt(irq) {
+            log::warn!("{:?}", err);
+        }
     }
 
+    /// Puts the system into the sleep state. For the states which preserve the memory contents
+    /// (S1-S3), the function returns once the system has woken up and the wake sequence is done.
     pub unsafe fn enter_sleep_state(
         &mut self,
         state: AcpiSleepState,
-    ) -> Result<(), AcpiSystemError> {
+    ) -> Result<WakeReason, AcpiSystemError> {
         log::info!("Entering sleep state: {:?}", state);
         let (sleep_type_a, sleep_type_b) = self.prepare_sleep_state(state)?;
-        self.dispatch_sleep_command(sleep_type_a, sleep_type_b)
+
+        match state {
+            // The CPU context is lost in S3, so the OS has to save it and resume through the
+            // firmware waking vector
+            AcpiSleepState::S3 => {
+                H::suspend(&mut || self.dispatch_sleep_command(state, sleep_type_a, sleep_type_b))?
+            }
+            _ => self.dispatch_sleep_command(state, sleep_type_a, sleep_type_b)?,
+        }
+
+        log::info!("Resumed from sleep state: {:?}", state);
+        self.leave_sleep_state(state)
     }
 
     fn configure_aml_interrupt_method(
@@ -247,7 +505,33 @@ impl<'a, H: Handler + 'a> AcpiSystem<'a, H> {
     ) -> Result<(), AcpiSystemError> {
         match action {
             EventAction::Nothing => Ok(()),
-            EventAction::EnterSleepState(state) => unsafe { self.enter_sleep_state(state) },
+            EventAction::EnterSleepState(state) => unsafe {
+                self.enter_sleep_state(state).map(|_| ())
+            },
+            EventAction::Reset => self.reset(),
+            EventAction::EvaluateMethod(path, args) => {
+                let args = aml::value::Args::from_list(args)?;
+                self.aml_context.invoke_method(&path, args)?;
+                Ok(())
+            }
+            EventAction::Notify(object, value) => {
+                self.notify_queue.push(object, value);
+                Ok(())
+            }
+            EventAction::EnableGpe(gpe_number) => self.enable_gpe(gpe_number, None),
+            EventAction::DisableGpe(gpe_number) => self.disable_gpe(gpe_number),
+            EventAction::Custom(token) => {
+                self.custom_events.push_back(token);
+                Ok(())
+            }
+            EventAction::Sequence(actions) => actions
+                .into_iter()
+                .try_for_each(|action| self.handle_event_action(action)),
         }
     }
+
+    /// Returns the oldest token an event handler has returned through [EventAction::Custom]
+    pub fn poll_custom_event(&mut self) -> Option<u32> {
+        self.custom_events.pop_front()
+    }
 }
@@ -0,0 +1,143 @@
+use acpi::AcpiHandler;
+use alloc::{format, string::String, vec::Vec};
+use aml::{value::Args, AmlError, AmlName, AmlValue, LevelType};
+
+use crate::{AcpiSystem, AcpiSystemError, Handler};
+
+// Default _STA value for devices which don't have one: present, enabled, shown in UI, functioning
+const DEFAULT_DEVICE_STATUS: u64 = 0x0F;
+const STATUS_PRESENT: u64 = 1 << 0;
+
+// Decodes a compressed EISA ID (e.g. 0x030AD041) into its string form ("PNP0A03")
+pub(crate) fn eisa_id_to_string(value: u64) -> String {
+    let id = (value as u32).swap_bytes();
+    let vendor = [
+        (((id >> 26) & 0x1F) as u8 + 0x40) as char,
+        (((id >> 21) & 0x1F) as u8 + 0x40) as char,
+        (((id >> 16) & 0x1F) as u8 + 0x40) as char,
+    ];
+    let product = id & 0xFFFF;
+
+    format!("{}{}{}{:04X}", vendor[0], vendor[1], vendor[2], product)
+}
+
+// Converts an ID object (_HID/_CID entry), which is either a string or an EISA ID
+pub(crate) fn device_id_from_value(value: &AmlValue) -> Option<String> {
+    match value {
+        AmlValue::Integer(id) => Some(eisa_id_to_string(*id)),
+        AmlValue::String(id) => Some(id.clone()),
+        _ => None,
+    }
+}
+
+impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
+    // Returns the names and full paths of the objects (not the child scopes) declared directly
+    // within `scope`
+    pub(crate) fn scope_objects(
+        &mut self,
+        scope: &AmlName,
+    ) -> Result<Vec<(String, AmlName)>, AcpiSystemError> {
+        // Namespace traversal only gives us the level names, so we have to know which levels
+        // lead to the requested scope
+        let mut ancestors = Vec::new();
+        let mut name = scope.clone();
+        while let Ok(parent) = name.parent() {
+            ancestors.push(parent.clone());
+            name = parent;
+        }
+
+        let mut objects = Vec::new();
+        self.aml_context.namespace.traverse(|level_name, level| {
+            if level_name == scope {
+                for &seg in level.values.keys() {
+                    let path = AmlName::from_name_seg(seg).resolve(scope)?;
+                    objects.push((seg.as_str().into(), path));
+                }
+                Ok(false)
+            } else {
+                Ok(ancestors.contains(level_name))
+            }
+        })?;
+
+        Ok(objects)
+    }
+
+    // Evaluates an object, treating a missing one as None
+    pub(crate) fn evaluate_object(
+        &mut self,
+        path: &AmlName,
+    ) -> Result<Option<AmlValue>, AcpiSystemError> {
+        match self.aml_context.invoke_method(path, Args::EMPTY) {
+            Ok(value) => Ok(Some(value)),
+            Err(AmlError::ValueDoesNotExist(_)) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    // Invokes a method, returns false if it doesn't exist
+    pub(crate) fn invoke_optional_method(
+        &mut self,
+        path: &AmlName,
+        args: Args,
+    ) -> Result<bool, AcpiSystemError> {
+        match self.aml_context.invoke_method(path, args) {
+            Ok(_) => Ok(true),
+            Err(AmlError::ValueDoesNotExist(_)) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub(crate) fn device_hid(
+        &mut self,
+        device: &AmlName,
+    ) -> Result<Option<String>, AcpiSystemError> {
+        let path = AmlName::from_str("_HID").unwrap().resolve(device)?;
+        Ok(self
+            .evaluate_object(&path)?
+            .and_then(|value| device_id_from_value(&value)))
+    }
+
+    // Returns the raw _STA value of a device
+    pub(crate) fn device_status(&mut self, device: &AmlName) -> Result<u64, AcpiSystemError> {
+        let path = AmlName::from_str("_STA").unwrap().resolve(device)?;
+        match self.evaluate_object(&path)? {
+            Some(value) => Ok(value.as_integer(&self.aml_context)?),
+            None => Ok(DEFAULT_DEVICE_STATUS),
+        }
+    }
+
+    // Returns the paths of all the Device objects in the namespace
+    pub(crate) fn device_paths(&mut self) -> Result<Vec<AmlName>, AcpiSystemError> {
+        let mut devices = Vec::new();
+        self.aml_context.namespace.traverse(|level_name, level| {
+            if matches!(level.typ, LevelType::Device) {
+                devices.push(level_name.clone());
+            }
+            Ok(true)
+        })?;
+        Ok(devices)
+    }
+
+    // Returns the paths of the present devices with the given _HID
+    pub(crate) fn find_devices(&mut self, hid: &str) -> Result<Vec<AmlName>, AcpiSystemError> {
+        let mut devices = Vec::new();
+        for device in self.device_paths()? {
+            // Broken firmware objects shouldn't prevent us from finding the other devices
+            match self.device_hid(&device) {
+                Ok(Some(id)) if id == hid => (),
+                Ok(_) => continue,
+                Err(error) => {
+                    log::warn!("{}._HID: {:?}", device, error);
+                    continue;
+                }
+            }
+
+            match self.device_status(&device) {
+                Ok(status) if status & STATUS_PRESENT != 0 => devices.push(device),
+                Ok(_) => log::trace!("{}: {} is not present", hid, device),
+                Err(error) => log::warn!("{}._STA: {:?}", device, error),
+            }
+        }
+        Ok(devices)
+    }
+}
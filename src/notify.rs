@@ -0,0 +1,218 @@
+use acpi::AcpiHandler;
+use alloc::{boxed::Box, collections::VecDeque, sync::Arc, vec::Vec};
+use aml::AmlName;
+use spinning_top::Spinlock;
+
+use crate::{AcpiSystem, AcpiSystemError, EventAction, Handler};
+
+pub(crate) type NotifyHandler<S> = Box<dyn FnMut(&mut S, u64) -> EventAction>;
+pub(crate) type GlobalNotifyHandler<S> = Box<dyn FnMut(&mut S, &AmlName, u64) -> EventAction>;
+
+// Queue of the Notify() operations executed by AML code, filled by NotifyForwarder while the
+// interpreter runs. The notifications are delivered to the handlers once it's done.
+#[derive(Clone, Default)]
+pub(crate) struct NotifyQueue {
+    inner: Arc<Spinlock<VecDeque<(AmlName, u64)>>>,
+}
+
+impl NotifyQueue {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn push(&self, object: AmlName, value: u64) {
+        self.inner.lock().push_back((object, value));
+    }
+
+    fn pop(&self) -> Option<(AmlName, u64)> {
+        self.inner.lock().pop_front()
+    }
+}
+
+// Wraps the OS's aml::Handler to catch the Notify() operations, everything else is passed through
+pub(crate) struct NotifyForwarder {
+    inner: Box<dyn aml::Handler>,
+    queue: NotifyQueue,
+}
+
+impl NotifyForwarder {
+    pub(crate) fn new(inner: Box<dyn aml::Handler>, queue: NotifyQueue) -> Self {
+        Self { inner, queue }
+    }
+}
+
+impl aml::Handler for NotifyForwarder {
+    fn read_u8(&self, address: usize) -> u8 {
+        self.inner.read_u8(address)
+    }
+    fn read_u16(&self, address: usize) -> u16 {
+        self.inner.read_u16(address)
+    }
+    fn read_u32(&self, address: usize) -> u32 {
+        self.inner.read_u32(address)
+    }
+    fn read_u64(&self, address: usize) -> u64 {
+        self.inner.read_u64(address)
+    }
+
+    fn write_u8(&mut self, address: usize, value: u8) {
+        self.inner.write_u8(address, value)
+    }
+    fn write_u16(&mut self, address: usize, value: u16) {
+        self.inner.write_u16(address, value)
+    }
+    fn write_u32(&mut self, address: usize, value: u32) {
+        self.inner.write_u32(address, value)
+    }
+    fn write_u64(&mut self, address: usize, value: u64) {
+        self.inner.write_u64(address, value)
+    }
+
+    fn read_io_u8(&self, port: u16) -> u8 {
+        self.inner.read_io_u8(port)
+    }
+    fn read_io_u16(&self, port: u16) -> u16 {
+        self.inner.read_io_u16(port)
+    }
+    fn read_io_u32(&self, port: u16) -> u32 {
+        self.inner.read_io_u32(port)
+    }
+
+    fn write_io_u8(&self, port: u16, value: u8) {
+        self.inner.write_io_u8(port, value)
+    }
+    fn write_io_u16(&self, port: u16, value: u16) {
+        self.inner.write_io_u16(port, value)
+    }
+    fn write_io_u32(&self, port: u16, value: u32) {
+        self.inner.write_io_u32(port, value)
+    }
+
+    fn read_pci_u8(&self, segment: u16, bus: u8, device: u8, function: u8, offset: u16) -> u8 {
+        self.inner
+            .read_pci_u8(segment, bus, device, function, offset)
+    }
+    fn read_pci_u16(&self, segment: u16, bus: u8, device: u8, function: u8, offset: u16) -> u16 {
+        self.inner
+            .read_pci_u16(segment, bus, device, function, offset)
+    }
+    fn read_pci_u32(&self, segment: u16, bus: u8, device: u8, function: u8, offset: u16) -> u32 {
+        self.inner
+            .read_pci_u32(segment, bus, device, function, offset)
+    }
+
+    fn write_pci_u8(
+        &self,
+        segment: u16,
+        bus: u8,
+        device: u8,
+        function: u8,
+        offset: u16,
+        value: u8,
+    ) {
+        self.inner
+            .write_pci_u8(segment, bus, device, function, offset, value)
+    }
+    fn write_pci_u16(
+        &self,
+        segment: u16,
+        bus: u8,
+        device: u8,
+        function: u8,
+        offset: u16,
+        value: u16,
+    ) {
+        self.inner
+            .write_pci_u16(segment, bus, device, function, offset, value)
+    }
+    fn write_pci_u32(
+        &self,
+        segment: u16,
+        bus: u8,
+        device: u8,
+        function: u8,
+        offset: u16,
+        value: u32,
+    ) {
+        self.inner
+            .write_pci_u32(segment, bus, device, function, offset, value)
+    }
+
+    fn stall(&self, microseconds: u64) {
+        self.inner.stall(microseconds)
+    }
+    fn sleep(&self, milliseconds: u64) {
+        self.inner.sleep(milliseconds)
+    }
+
+    fn handle_fatal_error(&self, fatal_type: u8, fatal_code: u32, fatal_arg: u64) {
+        self.inner
+            .handle_fatal_error(fatal_type, fatal_code, fatal_arg)
+    }
+
+    fn handle_notify(&self, object: &AmlName, value: u64) {
+        self.queue.push(object.clone(), value);
+        self.inner.handle_notify(object, value)
+    }
+}
+
+impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
+    /// Installs a handler for the notifications sent to a namespace object, replacing the one
+    /// installed previously
+    pub fn install_notify_handler(
+        &mut self,
+        aml_path: &str,
+        handler: NotifyHandler<Self>,
+    ) -> Result<(), AcpiSystemError> {
+        let path = AmlName::from_str(aml_path)?;
+        log::info!("Install notify handler: {}", path);
+
+        self.set_notify_handler(path, handler);
+        Ok(())
+    }
+
+    pub(crate) fn set_notify_handler(&mut self, path: AmlName, handler: NotifyHandler<Self>) {
+        self.notify_handlers.install(path, handler);
+    }
+
+    /// Removes the notify handler of a namespace object
+    pub fn remove_notify_handler(&mut self, aml_path: &str) -> Result<(), AcpiSystemError> {
+        let path = AmlName::from_str(aml_path)?;
+        self.notify_handlers.remove(&path);
+        Ok(())
+    }
+
+    /// Installs a handler which receives the notifications sent to any object, after the
+    /// object's own handler
+    pub fn install_global_notify_handler(&mut self, handler: GlobalNotifyHandler<Self>) {
+        self.global_notify_handler.install((), handler);
+    }
+
+    // Delivers the queued notifications to their handlers
+    pub(crate) fn process_notifications(&mut self) {
+        while let Some((object, value)) = self.notify_queue.pop() {
+            log::trace!("Notify({}, {:#x})", object, value);
+
+            let mut actions = Vec::new();
+            actions.extend(self.run_handler(
+                |system| &mut system.notify_handlers,
+                &object,
+                |system, handler| handler(system, value),
+            ));
+            actions.extend(self.run_handler(
+                |system| &mut system.global_notify_handler,
+                &(),
+                |system, handler| handler(system, &object, value),
+            ));
+            if actions.is_empty() {
+                log::debug!("No handler for Notify({}, {:#x})", object, value);
+            }
+
+            for action in actions {
+                if let Err(error) = self.handle_event_action(action) {
+                    log::warn!("Notify({}, {:#x}): {:?}", object, value, error);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use acpi::AcpiHandler;
+use alloc::{sync::Arc, vec, vec::Vec};
+use aml::{value::Args, AmlError, AmlName, AmlValue};
+use spinning_top::Spinlock;
+
+use crate::{AcpiSystem, AcpiSystemError, Handler, PciRootBridge};
+
+// 33DB4D5B-1FF7-401C-9657-7441C03DD766, in the byte order of the ToUUID() macro
+const PCI_HOST_BRIDGE_UUID: [u8; 16] = [
+    0x5B, 0x4D, 0xDB, 0x33, 0xF7, 0x1F, 0x1C, 0x40, 0x96, 0x57, 0x74, 0x41, 0xC0, 0x3D, 0xD7, 0x66,
+];
+const PCI_HOST_BRIDGE_REVISION: u64 = 1;
+// 0811B06E-4A27-44F9-8D60-3CBBC22E7B48
+const PLATFORM_WIDE_UUID: [u8; 16] = [
+    0x6E, 0xB0, 0x11, 0x08, 0x27, 0x4A, 0xF9, 0x44, 0x8D, 0x60, 0x3C, 0xBB, 0xC2, 0x2E, 0x7B, 0x48,
+];
+const PLATFORM_WIDE_REVISION: u64 = 1;
+const PATH_SYSTEM_BUS: &str = "\\_SB";
+
+// First DWORD of the capabilities buffer, input
+const OSC_QUERY_ENABLE: u32 = 1 << 0;
+// First DWORD of the capabilities buffer, output
+const OSC_REQUEST_ERROR: u32 = 1 << 1;
+const OSC_INVALID_UUID: u32 = 1 << 2;
+const OSC_INVALID_REVISION: u32 = 1 << 3;
+const OSC_CAPABILITIES_MASKED: u32 = 1 << 4;
+
+/// Support field of the PCI host bridge _OSC, tells the firmware which PCI features the OS
+/// implements
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PciOscSupport(pub u32);
+
+impl PciOscSupport {
+    pub const EXTENDED_CONFIG_SPACE: Self = Self(1 << 0);
+    pub const ASPM: Self = Self(1 << 1);
+    pub const CLOCK_PM: Self = Self(1 << 2);
+    pub const SEGMENTS: Self = Self(1 << 3);
+    pub const MSI: Self = Self(1 << 4);
+    pub const OPTIMIZED_BUFFER_FLUSH: Self = Self(1 << 5);
+    pub const ASPM_OPTIONALITY: Self = Self(1 << 6);
+    pub const ERROR_DISCONNECT_RECOVER: Self = Self(1 << 7);
+    /// _HPX Type 3 record support
+    pub const HOTPLUG_EXTENSIONS: Self = Self(1 << 8);
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// Control field of the PCI host bridge _OSC, the PCIe features the OS wants to control natively
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PciOscControl(pub u32);
+
+impl PciOscControl {
+    pub const NATIVE_HOTPLUG: Self = Self(1 << 0);
+    pub const SHPC_HOTPLUG: Self = Self(1 << 1);
+    pub const PME: Self = Self(1 << 2);
+    pub const AER: Self = Self(1 << 3);
+    pub const PCIE_CAPABILITY: Self = Self(1 << 4);
+    pub const LTR: Self = Self(1 << 5);
+    pub const ERROR_DISCONNECT_RECOVER: Self = Self(1 << 7);
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+/// Platform-wide \_SB._OSC capabilities
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PlatformOscCapabilities(pub u32);
+
+impl PlatformOscCapabilities {
+    pub const PROCESSOR_AGGREGATOR: Self = Self(1 << 0);
+    pub const PPC_OST: Self = Self(1 << 1);
+    pub const PR3: Self = Self(1 << 2);
+    pub const HOTPLUG_OST: Self = Self(1 << 3);
+    pub const APEI: Self = Self(1 << 4);
+    pub const CPPC: Self = Self(1 << 5);
+    pub const CPPC_V2: Self = Self(1 << 6);
+    pub const PLATFORM_COORDINATED_LPI: Self = Self(1 << 7);
+    pub const OS_INITIATED_LPI: Self = Self(1 << 8);
+    pub const FAST_THERMAL_SAMPLING: Self = Self(1 << 9);
+    pub const OVER_16_PSTATES: Self = Self(1 << 10);
+    pub const GENERIC_EVENT_DEVICE: Self = Self(1 << 11);
+    pub const CPPC_DIVERSE_HIGHEST_PERFORMANCE: Self = Self(1 << 12);
+    pub const INTERRUPT_RESOURCE_SOURCE: Self = Self(1 << 13);
+    pub const CPPC_FLEXIBLE_ADDRESS_SPACE: Self = Self(1 << 14);
+    pub const GENERIC_INITIATOR: Self = Self(1 << 17);
+    pub const NATIVE_USB4: Self = Self(1 << 18);
+    pub const PLATFORM_RUNTIME_MECHANISM: Self = Self(1 << 21);
+    pub const FFH_OPREGION: Self = Self(1 << 22);
+
+    /// Capabilities implemented by this crate itself
+    pub const BUILTIN: Self = Self::GENERIC_EVENT_DEVICE;
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+fn dwords_to_buffer(dwords: &[u32]) -> AmlValue {
+    let bytes = dwords
+        .iter()
+        .flat_map(|dword| dword.to_le_bytes())
+        .collect();
+    AmlValue::Buffer(Arc::new(Spinlock::new(bytes)))
+}
+
+impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
+    // Evaluates _OSC(UUID, Revision, Count, Capabilities) of a device, returns the capabilities
+    // buffer the firmware has returned
+    pub(crate) fn evaluate_osc(
+        &mut self,
+        device: &AmlName,
+        uuid: &[u8; 16],
+        revision: u64,
+        capabilities: &[u32],
+    ) -> Result<Vec<u32>, AcpiSystemError> {
+        let path = AmlName::from_str("_OSC").unwrap().resolve(device)?;
+        let args = Args::from_list(vec![
+            AmlValue::Buffer(Arc::new(Spinlock::new(uuid.to_vec()))),
+            AmlValue::Integer(revision),
+            AmlValue::Integer(capabilities.len() as u64),
+            dwords_to_buffer(capabilities),
+        ])
+        .unwrap();
+
+        let AmlValue::Buffer(result) = self.aml_context.invoke_method(&path, args)? else {
+            return Err(AcpiSystemError::InvalidOscResponse);
+        };
+        let result = result
+            .lock()
+            .chunks_exact(4)
+            .map(|dword| u32::from_le_bytes(dword.try_into().unwrap()))
+            .collect::<Vec<_>>();
+
+        if result.len() < capabilities.len() {
+            return Err(AcpiSystemError::InvalidOscResponse);
+        }
+
+        // A masked capability isn't an error, the caller just gets less than it has asked for
+        let status = result[0];
+        if status & (OSC_REQUEST_ERROR | OSC_INVALID_UUID | OSC_INVALID_REVISION) != 0 {
+            return Err(AcpiSystemError::OscFailed(status));
+        }
+        if status & OSC_CAPABILITIES_MASKED != 0 {
+            log::debug!("{}: capabilities masked by the firmware", path);
+        }
+
+        Ok(result)
+    }
+
+    /// Adds capabilities the OS implements itself (e.g. CPPC) to the ones advertised through
+    /// \_SB._OSC. Has to be called before [AcpiSystem::initialize].
+    pub fn set_platform_osc_support(&mut self, support: PlatformOscCapabilities) {
+        self.platform_osc_support = support.union(PlatformOscCapabilities::BUILTIN);
+    }
+
+    /// Returns the platform-wide capabilities the firmware has acknowledged through \_SB._OSC
+    /// during [AcpiSystem::initialize]
+    pub fn platform_osc_capabilities(&self) -> PlatformOscCapabilities {
+        self.platform_osc_granted
+    }
+
+    // Tells the firmware which platform-wide features the OS supports, querying it first like
+    // for the PCI _OSC. Firmware without \_SB._OSC simply grants nothing.
+    pub(crate) fn negotiate_platform_osc(&mut self) -> Result<(), AcpiSystemError> {
+        let device = AmlName::from_str(PATH_SYSTEM_BUS).unwrap();
+        let support = self.platform_osc_support;
+
+        let query = match self.evaluate_osc(
+            &device,
+            &PLATFORM_WIDE_UUID,
+            PLATFORM_WIDE_REVISION,
+            &[OSC_QUERY_ENABLE, support.0],
+        ) {
+            Err(AcpiSystemError::AmlError(AmlError::ValueDoesNotExist(_))) => {
+                log::info!("No platform-wide _OSC");
+                return Ok(());
+            }
+            result => result?,
+        };
+        let available = query[1] & support.0;
+
+        let result = self.evaluate_osc(
+            &device,
+            &PLATFORM_WIDE_UUID,
+            PLATFORM_WIDE_REVISION,
+            &[0, available],
+        )?;
+        self.platform_osc_granted = PlatformOscCapabilities(result[1] & available);
+        log::info!(
+            "Platform _OSC: supported {:#x}, granted {:#x}",
+            support.0,
+            self.platform_osc_granted.0
+        );
+
+        Ok(())
+    }
+
+    /// Asks the firmware for native control of the requested PCIe features of a root bridge
+    /// through its _OSC, returns the features the OS has been granted control of. The firmware
+    /// is queried first, so only the features it is willing to give up are actually requested.
+    /// Most firmware only grants control if PCIe capability structure control is requested as
+    /// well and the OS reports ASPM, Clock PM and MSI support.
+    pub fn negotiate_pci_osc(
+        &mut self,
+        root_bridge: &PciRootBridge,
+        support: PciOscSupport,
+        requested: PciOscControl,
+    ) -> Result<PciOscControl, AcpiSystemError> {
+        let device = &root_bridge.path;
+
+        let query = self.evaluate_osc(
+            device,
+            &PCI_HOST_BRIDGE_UUID,
+            PCI_HOST_BRIDGE_REVISION,
+            &[OSC_QUERY_ENABLE, support.0, requested.0],
+        )?;
+        let available = query[2] & requested.0;
+        log::debug!(
+            "{}: _OSC control requested {:#x}, available {:#x}",
+            device,
+            requested.0,
+            available
+        );
+
+        if available == 0 {
+            return Ok(PciOscControl::default());
+        }
+
+        let result = self.evaluate_osc(
+            device,
+            &PCI_HOST_BRIDGE_UUID,
+            PCI_HOST_BRIDGE_REVISION,
+            &[0, support.0, available],
+        )?;
+        let granted = PciOscControl(result[2] & available);
+        log::info!("{}: _OSC granted PCIe control {:#x}", device, granted.0);
+
+        Ok(granted)
+    }
+}
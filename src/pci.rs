@@ -0,0 +1,127 @@
+use core::ops::RangeInclusive;
+
+use acpi::AcpiHandler;
+use alloc::vec::Vec;
+use aml::{
+    resource::{self, AddressSpaceResourceType, Resource},
+    AmlName,
+};
+
+use crate::{AcpiSystem, AcpiSystemError, Handler};
+
+const HID_PCI_ROOT_BRIDGE: &str = "PNP0A03";
+const HID_PCIE_ROOT_BRIDGE: &str = "PNP0A08";
+
+/// Address window decoded by a PCI root bridge
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PciWindow {
+    /// Address on the PCI side
+    pub base: u64,
+    pub length: u64,
+    /// Offset to add to a PCI address to get the corresponding CPU address
+    pub translation_offset: u64,
+}
+
+/// PCI/PCIe host bridge described in the namespace
+#[derive(Clone, Debug)]
+pub struct PciRootBridge {
+    /// Path to pass to [AcpiSystem::pci_route]
+    pub path: AmlName,
+    /// _SEG, the PCI segment group
+    pub segment: u16,
+    /// _BBN, the bus number of the root bus
+    pub base_bus: u8,
+    /// Bus numbers decoded by the bridge, from _CRS
+    pub bus_numbers: Option<RangeInclusive<u8>>,
+    pub io_windows: Vec<PciWindow>,
+    pub memory_windows: Vec<PciWindow>,
+}
+
+impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
+    /// Returns the present PCI (PNP0A03) and PCI Express (PNP0A08) root bridges along with their
+    /// bus numbers and resource windows
+    pub fn pci_root_bridges(&mut self) -> Result<Vec<PciRootBridge>, AcpiSystemError> {
+        let bridges = self
+            .devices()?
+            .filter(|device| {
+                let is_root_bridge =
+                    |id: &str| id == HID_PCI_ROOT_BRIDGE || id == HID_PCIE_ROOT_BRIDGE;
+
+                device.status.is_present()
+                    && (device.hid.as_deref().is_some_and(is_root_bridge)
+                        || device.cids.iter().any(|id| is_root_bridge(id)))
+            })
+            .map(|device| device.path)
+            .collect::<Vec<_>>();
+
+        let mut root_bridges = Vec::new();
+        for bridge in bridges {
+            match self.pci_root_bridge(bridge.clone()) {
+                Ok(root_bridge) => root_bridges.push(root_bridge),
+                Err(error) => log::warn!("{}: {:?}", bridge, error),
+            }
+        }
+        Ok(root_bridges)
+    }
+
+    fn pci_root_bridge(&mut self, path: AmlName) -> Result<PciRootBridge, AcpiSystemError> {
+        let segment = self.device_integer(&path, "_SEG")?.unwrap_or(0) as u16;
+        let base_bus = self.device_integer(&path, "_BBN")?.unwrap_or(0) as u8;
+
+        let mut root_bridge = PciRootBridge {
+            path,
+            segment,
+            base_bus,
+            bus_numbers: None,
+            io_windows: Vec::new(),
+            memory_windows: Vec::new(),
+        };
+
+        let crs_path = AmlName::from_str("_CRS")
+            .unwrap()
+            .resolve(&root_bridge.path)?;
+        let Some(crs) = self.evaluate_object(&crs_path)? else {
+            return Ok(root_bridge);
+        };
+
+        for resource in resource::resource_descriptor_list(&crs)? {
+            let Resource::AddressSpace(space) = resource else {
+                continue;
+            };
+            if space.length == 0 {
+                continue;
+            }
+
+            let window = PciWindow {
+                base: space.address_range.0,
+                length: space.length,
+                translation_offset: space.translation_offset,
+            };
+
+            match space.resource_type {
+                AddressSpaceResourceType::BusNumberRange => {
+                    let first = window.base as u8;
+                    let last = (window.base + window.length - 1) as u8;
+                    root_bridge.bus_numbers.replace(first..=last);
+                }
+                AddressSpaceResourceType::IORange => root_bridge.io_windows.push(window),
+                AddressSpaceResourceType::MemoryRange => root_bridge.memory_windows.push(window),
+            }
+        }
+
+        Ok(root_bridge)
+    }
+
+    // Evaluates an optional integer object of a device
+    fn device_integer(
+        &mut self,
+        device: &AmlName,
+        name: &str,
+    ) -> Result<Option<u64>, AcpiSystemError> {
+        let path = AmlName::from_str(name).unwrap().resolve(device)?;
+        match self.evaluate_object(&path)? {
+            Some(value) => Ok(Some(value.as_integer(&self.aml_context)?)),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use core::ops::RangeInclusive;
+
+use acpi::AcpiHandler;
+
+use crate::{AcpiSystem, AcpiSystemError, FixedEvent, Handler};
+
+const CMOS_INDEX_PORT: u16 = 0x70;
+const CMOS_DATA_PORT: u16 = 0x71;
+
+// CMOS RTC registers
+const RTC_SECONDS_ALARM: u8 = 0x01;
+const RTC_MINUTES_ALARM: u8 = 0x03;
+const RTC_HOURS_ALARM: u8 = 0x05;
+const RTC_STATUS_B: u8 = 0x0B;
+const RTC_STATUS_C: u8 = 0x0C;
+
+// Status Register B
+const RTC_B_24_HOUR: u8 = 1 << 1;
+const RTC_B_BINARY: u8 = 1 << 2;
+const RTC_B_ALARM_INTERRUPT: u8 = 1 << 5;
+
+const RTC_HOUR_PM: u8 = 1 << 7;
+
+/// Time at which the RTC alarm fires. Fields set to `None` match any value, the day of month and
+/// month can only be specified if the FADT reports their alarm registers. The day of month and
+/// month start at 1.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RtcAlarm {
+    pub second: Option<u8>,
+    pub minute: Option<u8>,
+    /// Hour in the 24-hour format
+    pub hour: Option<u8>,
+    pub day: Option<u8>,
+    pub month: Option<u8>,
+}
+
+fn to_bcd(value: u8) -> u8 {
+    ((value / 10) << 4) | (value % 10)
+}
+
+impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
+    fn cmos_read(index: u8) -> u8 {
+        H::io_write_u8(CMOS_INDEX_PORT, index);
+        H::io_read_u8(CMOS_DATA_PORT)
+    }
+
+    fn cmos_write(index: u8, value: u8) {
+        H::io_write_u8(CMOS_INDEX_PORT, index);
+        H::io_write_u8(CMOS_DATA_PORT, value);
+    }
+
+    // Returns the value in the format used by the RTC (BCD or binary)
+    fn rtc_encode(status_b: u8, value: u8) -> u8 {
+        if status_b & RTC_B_BINARY != 0 {
+            value
+        } else {
+            to_bcd(value)
+        }
+    }
+
+    fn rtc_encode_hour(status_b: u8, hour: u8) -> u8 {
+        if status_b & RTC_B_24_HOUR != 0 {
+            return Self::rtc_encode(status_b, hour);
+        }
+
+        let pm = if hour >= 12 { RTC_HOUR_PM } else { 0 };
+        let hour = match hour % 12 {
+            0 => 12,
+            hour => hour,
+        };
+        Self::rtc_encode(status_b, hour) | pm
+    }
+
+    // The RTC alarm is signaled through the RTC fixed event, which FIX_RTC reports as missing
+    fn check_rtc_alarm_supported(&self) -> Result<(), AcpiSystemError> {
+        if self.is_hardware_reduced() {
+            return Err(AcpiSystemError::NotSupportedOnReducedHardware);
+        }
+
+        let flags = self.fadt.flags;
+        if flags.no_rtc_wake_in_fixed_register_space() {
+            return Err(AcpiSystemError::RtcAlarmNotSupported);
+        }
+
+        Ok(())
+    }
+
+    /// Programs the CMOS RTC alarm and enables the RTC fixed event, which also allows the alarm
+    /// to wake the system up from sleep states. The alarm fires once the RTC time matches it.
+    pub fn set_rtc_alarm(&mut self, alarm: RtcAlarm) -> Result<(), AcpiSystemError> {
+        self.check_rtc_alarm_supported()?;
+
+        let day_alarm = self.fadt.day_alarm;
+        let month_alarm = self.fadt.month_alarm;
+        if (alarm.day.is_some() && day_alarm == 0) || (alarm.month.is_some() && month_alarm == 0) {
+            return Err(AcpiSystemError::UnsupportedRtcAlarm(alarm));
+        }
+
+        // Out of range values would be encoded as "don't care" or garbage
+        let in_range = |value: Option<u8>, range: RangeInclusive<u8>| match value {
+            Some(value) => range.contains(&value),
+            None => true,
+        };
+        if !in_range(alarm.second, 0..=59)
+            || !in_range(alarm.minute, 0..=59)
+            || !in_range(alarm.hour, 0..=23)
+            || !in_range(alarm.day, 1..=31)
+            || !in_range(alarm.month, 1..=12)
+        {
+            return Err(AcpiSystemError::UnsupportedRtcAlarm(alarm));
+        }
+
+        log::info!("Set RTC alarm: {:?}", alarm);
+        let status_b = Self::cmos_read(RTC_STATUS_B);
+
+        // Disable the alarm interrupt while the alarm is being changed
+        Self::cmos_write(RTC_STATUS_B, status_b & !RTC_B_ALARM_INTERRUPT);
+
+        // Values 0xC0-0xFF in the alarm registers are "don't care"
+        let encode = |value: Option<u8>| value.map_or(0xFF, |v| Self::rtc_encode(status_b, v));
+        Self::cmos_write(RTC_SECONDS_ALARM, encode(alarm.second));
+        Self::cmos_write(RTC_MINUTES_ALARM, encode(alarm.minute));
+        Self::cmos_write(
+            RTC_HOURS_ALARM,
+            alarm
+                .hour
+                .map_or(0xFF, |hour| Self::rtc_encode_hour(status_b, hour)),
+        );
+        // The day and month alarms, when present, treat 0 as "don't care" instead
+        if day_alarm != 0 {
+            Self::cmos_write(
+                day_alarm,
+                alarm.day.map_or(0, |v| Self::rtc_encode(status_b, v)),
+            );
+        }
+        if month_alarm != 0 {
+            Self::cmos_write(
+                month_alarm,
+                alarm.month.map_or(0, |v| Self::rtc_encode(status_b, v)),
+            );
+        }
+
+        // Drop the stale alarm flag, then enable the interrupt again
+        Self::cmos_read(RTC_STATUS_C);
+        Self::cmos_write(RTC_STATUS_B, status_b | RTC_B_ALARM_INTERRUPT);
+
+        FixedEvent::RTC.status_register.clear_status(self)?;
+        FixedEvent::RTC.enable_register.set(self, true)
+    }
+
+    /// Disables the CMOS RTC alarm and the RTC fixed event
+    pub fn cancel_rtc_alarm(&mut self) -> Result<(), AcpiSystemError> {
+        self.check_rtc_alarm_supported()?;
+
+        log::info!("Cancel RTC alarm");
+        let status_b = Self::cmos_read(RTC_STATUS_B);
+        Self::cmos_write(RTC_STATUS_B, status_b & !RTC_B_ALARM_INTERRUPT);
+        Self::cmos_read(RTC_STATUS_C);
+
+        FixedEvent::RTC.enable_register.set(self, false)?;
+        FixedEvent::RTC.status_register.clear_status(self)
+    }
+
+    // Called from the SCI handler on RTC_STS, reading Status Register C acknowledges the alarm
+    pub(crate) fn handle_rtc_alarm(&mut self) {
+        Self::cmos_read(RTC_STATUS_C);
+    }
+}
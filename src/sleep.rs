@@ -1,10 +1,13 @@
-use acpi::AcpiHandler;
+use acpi::{
+    address::{AddressSpace, GenericAddress},
+    AcpiHandler,
+};
 use alloc::vec;
 use aml::{AmlError, AmlName, AmlValue};
 
 use crate::{
     hardware::{AcpiBitRangeRegister, AcpiBitRegister, AcpiRegister},
-    AcpiSystem, AcpiSystemError, Handler,
+    AcpiSystem, AcpiSystemError, FixedEvent, Handler, WakeReason,
 };
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -18,9 +21,35 @@ pub enum AcpiSleepState {
     S5 = 5,
 }
 
+/// Describes who is responsible for saving and restoring the memory image in S4
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HibernateMode {
+    /// The firmware saves the memory image itself and resumes through the firmware waking
+    /// vector, the same way as S3
+    S4Bios,
+    /// The OS writes the memory image before entering S4 and restores it after the next boot,
+    /// calling [AcpiSystem::leave_sleep_state] afterwards
+    OsDirected,
+}
+
 const SLEEP_STATE_NAMES: &[&str] = &["\\_S0_", "\\_S1_", "\\_S2_", "\\_S3_", "\\_S4_", "\\_S5_"];
 const PATH_PREPARE_TO_SLEEP: &str = "\\_PTS";
 const PATH_SYSTEM_STATUS: &str = "\\_SI._SST";
+const PATH_WAKE: &str = "\\_WAK";
+
+// _SST argument values
+const SST_INDICATOR_OFF: u64 = 0;
+const SST_WORKING: u64 = 1;
+const SST_WAKING: u64 = 2;
+const SST_SLEEPING: u64 = 3;
+const SST_SLEEP_CONTEXT: u64 = 4;
+
+// Fields of the FADT SLEEP_CONTROL_REG and SLEEP_STATUS_REG, used instead of PM1x on
+// hardware-reduced platforms
+const SLEEP_CONTROL_TYPE_SHIFT: u8 = 2;
+const SLEEP_CONTROL_TYPE_MASK: u8 = 0x1C;
+const SLEEP_CONTROL_ENABLE: u8 = 1 << 5;
+const SLEEP_STATUS_WAKE: u8 = 1 << 7;
 
 impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
     fn sleep_type_data(&self, state: AcpiSleepState) -> Result<(u8, u8), AcpiSystemError> {
@@ -42,8 +71,15 @@ impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
         let elements = elements.lock();
 
         match elements.len() {
-            0 => todo!(),
-            1 => todo!(),
+            0 => Err(AcpiSystemError::InvalidSleepMethod(name)),
+            1 => {
+                // Some firmware packs both values into a single integer
+                let value = elements[0]
+                    .as_integer(&self.aml_context)
+                    .map_err(|_| AcpiSystemError::InvalidSleepMethod(name))?;
+
+                Ok((value as u8, (value >> 8) as u8))
+            }
             _ => {
                 let val_a = elements[0]
                     .as_integer(&self.aml_context)
@@ -59,48 +95,167 @@ impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
         }
     }
 
-    pub(crate) unsafe fn prepare_sleep_state(
+    // Invokes one of the sleep/wake control methods, which are all optional
+    fn invoke_sleep_method(
         &mut self,
-        state: AcpiSleepState,
-    ) -> Result<(u8, u8), AcpiSystemError> {
-        let sleep_types = self.sleep_type_data(state)?;
-
-        // Invoke \_PTS (Prepare to sleep)
-        let args = aml::value::Args::from_list(vec![AmlValue::Integer(state as _)]).unwrap();
-        let path = AmlName::from_str(PATH_PREPARE_TO_SLEEP).unwrap();
+        name: &'static str,
+        value: u64,
+    ) -> Result<(), AcpiSystemError> {
+        let args = aml::value::Args::from_list(vec![AmlValue::Integer(value)]).unwrap();
+        let path = AmlName::from_str(name).unwrap();
 
         if let Err(err) = self.aml_context.invoke_method(&path, args) {
             if !matches!(err, AmlError::ValueDoesNotExist(_)) {
                 return Err(AcpiSystemError::AmlError(err));
             }
 
-            log::warn!("{}: {:?}", PATH_PREPARE_TO_SLEEP, err);
+            log::warn!("{}: {:?}", name, err);
         }
 
+        Ok(())
+    }
+
+    pub(crate) unsafe fn prepare_sleep_state(
+        &mut self,
+        state: AcpiSleepState,
+    ) -> Result<(u8, u8), AcpiSystemError> {
+        let sleep_types = self.sleep_type_data(state)?;
+
+        // Tell the firmware where to resume, don't even try entering S3 if the OS can't resume
+        if state == AcpiSleepState::S3 {
+            let vector = H::resume_trampoline().ok_or(AcpiSystemError::ResumeNotSupported)?;
+            self.set_firmware_waking_vector(vector)?;
+        }
+
+        // Invoke \_PTS (Prepare to sleep)
+        self.invoke_sleep_method(PATH_PREPARE_TO_SLEEP, state as u64)?;
+
         // Setup the argument to the _SST method (System STatus)
         let sst_value = match state {
-            AcpiSleepState::S0 => todo!(),
-            AcpiSleepState::S1 | AcpiSleepState::S2 | AcpiSleepState::S3 => todo!(),
-            AcpiSleepState::S4 => todo!(),
-            AcpiSleepState::S5 => 0, /* ACPI_SST_INDICATOR_OFF */
+            AcpiSleepState::S0 => SST_WORKING,
+            AcpiSleepState::S1 | AcpiSleepState::S2 | AcpiSleepState::S3 => SST_SLEEPING,
+            AcpiSleepState::S4 => SST_SLEEP_CONTEXT,
+            AcpiSleepState::S5 => SST_INDICATOR_OFF,
         };
 
-        let path = AmlName::from_str(PATH_SYSTEM_STATUS).unwrap();
-        let args = aml::value::Args::from_list(vec![AmlValue::Integer(sst_value as _)]).unwrap();
+        self.invoke_sleep_method(PATH_SYSTEM_STATUS, sst_value)?;
 
-        if let Err(err) = self.aml_context.invoke_method(&path, args) {
-            if !matches!(err, AmlError::ValueDoesNotExist(_)) {
-                return Err(AcpiSystemError::AmlError(err));
+        // The fixed event enables are not guaranteed to survive the sleep state
+        if !self.is_hardware_reduced() {
+            self.saved_pm1_enable = self.read_register(AcpiRegister::Pm1Enable)?;
+        }
+
+        Ok(sleep_types)
+    }
+
+    /// Enters S4 using the requested [HibernateMode]. For [HibernateMode::OsDirected], the OS must
+    /// have written its memory image already, as the system is powered down. For
+    /// [HibernateMode::S4Bios], the function returns once the system has resumed.
+    pub unsafe fn enter_hibernation(
+        &mut self,
+        mode: HibernateMode,
+    ) -> Result<WakeReason, AcpiSystemError> {
+        let state = AcpiSleepState::S4;
+        log::info!("Entering sleep state: {:?} ({:?})", state, mode);
+
+        match mode {
+            HibernateMode::OsDirected => {
+                let (sleep_type_a, sleep_type_b) = self.prepare_sleep_state(state)?;
+                self.dispatch_sleep_command(state, sleep_type_a, sleep_type_b)?;
             }
+            HibernateMode::S4Bios => {
+                if !self.s4bios_supported() {
+                    return Err(AcpiSystemError::S4BiosNotSupported);
+                }
+                let vector = H::resume_trampoline().ok_or(AcpiSystemError::ResumeNotSupported)?;
+                self.set_firmware_waking_vector(vector)?;
 
-            log::warn!("{}: {:?}", PATH_SYSTEM_STATUS, err);
+                self.prepare_sleep_state(state)?;
+                H::suspend(&mut || self.acpi_hw_s4bios_sleep())?;
+            }
         }
 
-        Ok(sleep_types)
+        log::info!("Resumed from sleep state: {:?}", state);
+        self.leave_sleep_state(state)
+    }
+
+    /// Runs the wake sequence after the system has resumed from the sleep state:
+    /// evaluates \_WAK, restores the runtime GPEs and fixed events and reports the system as
+    /// working through \_SI._SST. This is done by [AcpiSystem::enter_sleep_state] itself for
+    /// S3 and by [AcpiSystem::enter_hibernation] for S4BIOS, but OS-directed S4 requires the
+    /// OS to call this once the memory image is restored.
+    pub fn leave_sleep_state(
+        &mut self,
+        state: AcpiSleepState,
+    ) -> Result<WakeReason, AcpiSystemError> {
+        // Not knowing the reason is no excuse to leave the system half-awake
+        let sources = self.read_wake_sources().unwrap_or_else(|error| {
+            log::warn!("Could not read wake sources: {:?}", error);
+            vec![]
+        });
+        log::info!("Wake sources: {:?}", sources);
+
+        let hardware_reduced = self.is_hardware_reduced();
+
+        if hardware_reduced {
+            self.acpi_hw_extended_wake_prep()?;
+        } else {
+            self.acpi_hw_legacy_wake_prep()?;
+        }
+
+        // The firmware may have left the system in legacy mode during resume
+        self.enable_acpi()?;
+
+        self.invoke_sleep_method(PATH_SYSTEM_STATUS, SST_WAKING)?;
+        self.invoke_sleep_method(PATH_WAKE, state as u64)?;
+
+        if hardware_reduced {
+            self.acpi_hw_extended_wake()?;
+        } else {
+            self.acpi_hw_legacy_wake()?;
+        }
+
+        self.invoke_sleep_method(PATH_SYSTEM_STATUS, SST_WORKING)?;
+
+        Ok(WakeReason { state, sources })
+    }
+
+    fn acpi_hw_legacy_wake(&mut self) -> Result<(), AcpiSystemError> {
+        // Clear wake status
+        AcpiBitRegister::WAKE_STATUS.clear_status(self)?;
+
+        self.enable_runtime_gpes()?;
+        self.write_register(AcpiRegister::Pm1Enable, self.saved_pm1_enable)?;
+
+        // Don't let the button press which woke the system up be handled as a regular one
+        let power_button_status = &FixedEvent::POWER_BUTTON.status_register;
+        self.write_register(
+            AcpiRegister::Pm1Status,
+            power_button_status.set_raw(0, true),
+        )
+    }
+
+    fn acpi_hw_legacy_wake_prep(&mut self) -> Result<(), AcpiSystemError> {
+        let sleep_type_reg = &AcpiBitRangeRegister::SLEEP_TYPE;
+        let sleep_enable_reg = &AcpiBitRegister::SLEEP_ENABLE;
+
+        // Put the S0 values back into SLP_TYP, the \_S0_ object is optional though
+        let Ok((sleep_type_a, sleep_type_b)) = self.sleep_type_data(AcpiSleepState::S0) else {
+            return Ok(());
+        };
+
+        let mut pm1_control = self.read_register(AcpiRegister::Pm1Control)?;
+        pm1_control = sleep_enable_reg.set_raw(pm1_control, false);
+
+        self.write_pm1_control(
+            sleep_type_reg.set_raw(pm1_control, sleep_type_a as u32),
+            sleep_type_reg.set_raw(pm1_control, sleep_type_b as u32),
+        )
     }
 
     unsafe fn acpi_hw_legacy_sleep(
         &mut self,
+        state: AcpiSleepState,
         sleep_type_a: u8,
         sleep_type_b: u8,
     ) -> Result<(), AcpiSystemError> {
@@ -110,9 +265,9 @@ impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
         self.clear_fixed_events()?;
 
         // Clear wake status
-        AcpiBitRegister::WAKE_STATUS.set(self, true)?;
-        // TODO disable all GPEs
-        // TODO enable all wakeup GPEs
+        AcpiBitRegister::WAKE_STATUS.clear_status(self)?;
+
+        self.enable_wake_gpes()?;
 
         // Get current pm1a control value
         let mut pm1_control = self.read_register(AcpiRegister::Pm1Control)?;
@@ -136,11 +291,108 @@ impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
             sleep_enable_reg.set_raw(pm1b_control, true),
         )?;
 
+        // The CPU context is preserved in S1/S2, so the execution continues here once the system
+        // starts waking up
+        if matches!(state, AcpiSleepState::S1 | AcpiSleepState::S2) {
+            while !AcpiBitRegister::WAKE_STATUS.get(self)? {
+                core::hint::spin_loop();
+            }
+            return Ok(());
+        }
+
+        H::halt()
+    }
+
+    fn sleep_registers(&self) -> Result<(GenericAddress, GenericAddress), AcpiSystemError> {
+        let control = self.fadt.sleep_control_register()?;
+        let status = self.fadt.sleep_status_register()?;
+
+        control
+            .zip(status)
+            .ok_or(AcpiSystemError::MissingSleepRegisters)
+    }
+
+    fn acpi_hw_extended_wake(&mut self) -> Result<(), AcpiSystemError> {
+        let (_, sleep_status) = self.sleep_registers()?;
+
+        // Clear wake status
+        Self::write_address(sleep_status, SLEEP_STATUS_WAKE as u64)
+    }
+
+    fn acpi_hw_extended_wake_prep(&mut self) -> Result<(), AcpiSystemError> {
+        let (sleep_control, _) = self.sleep_registers()?;
+
+        // Put the S0 value back into SLP_TYP, the \_S0_ object is optional though
+        let Ok((sleep_type, _)) = self.sleep_type_data(AcpiSleepState::S0) else {
+            return Ok(());
+        };
+
+        let sleep_control_value =
+            (sleep_type << SLEEP_CONTROL_TYPE_SHIFT) & SLEEP_CONTROL_TYPE_MASK;
+        Self::write_address(sleep_control, sleep_control_value as u64)
+    }
+
+    // Hardware-reduced platforms only have a single SLP_TYP value, the second one from \_Sx is
+    // ignored
+    unsafe fn acpi_hw_extended_sleep(
+        &mut self,
+        state: AcpiSleepState,
+        sleep_type: u8,
+    ) -> Result<(), AcpiSystemError> {
+        let (sleep_control, sleep_status) = self.sleep_registers()?;
+
+        // Clear wake status
+        Self::write_address(sleep_status, SLEEP_STATUS_WAKE as u64)?;
+
+        unsafe {
+            H::flush_cpu_cache();
+        }
+
+        // SLP_TYP and SLP_EN are written together, unlike PM1_CNT
+        let sleep_control_value = ((sleep_type << SLEEP_CONTROL_TYPE_SHIFT)
+            & SLEEP_CONTROL_TYPE_MASK)
+            | SLEEP_CONTROL_ENABLE;
+        Self::write_address(sleep_control, sleep_control_value as u64)?;
+
+        // The CPU context is preserved in S1/S2, so the execution continues here once the system
+        // starts waking up
+        if matches!(state, AcpiSleepState::S1 | AcpiSleepState::S2) {
+            while Self::read_address(sleep_status)? as u8 & SLEEP_STATUS_WAKE == 0 {
+                core::hint::spin_loop();
+            }
+            return Ok(());
+        }
+
+        H::halt()
+    }
+
+    // AcpiEnterSleepStateS4bios
+    unsafe fn acpi_hw_s4bios_sleep(&mut self) -> Result<(), AcpiSystemError> {
+        self.clear_fixed_events()?;
+
+        // Clear wake status
+        AcpiBitRegister::WAKE_STATUS.clear_status(self)?;
+
+        self.enable_wake_gpes()?;
+
+        unsafe {
+            H::flush_cpu_cache();
+        }
+
+        // Ask the firmware to save the memory image and power the system down
+        Self::write_address_space(
+            AddressSpace::SystemIo,
+            self.fadt.smi_cmd_port as u64,
+            8,
+            self.fadt.s4bios_req as u64,
+        )?;
+
         H::halt()
     }
 
     pub(crate) unsafe fn dispatch_sleep_command(
         &mut self,
+        state: AcpiSleepState,
         sleep_type_a: u8,
         sleep_type_b: u8,
     ) -> Result<(), AcpiSystemError> {
@@ -151,7 +403,11 @@ impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
             ));
         }
 
-        self.acpi_hw_legacy_sleep(sleep_type_a, sleep_type_b)?;
+        if self.is_hardware_reduced() {
+            self.acpi_hw_extended_sleep(state, sleep_type_a)?;
+        } else {
+            self.acpi_hw_legacy_sleep(state, sleep_type_a, sleep_type_b)?;
+        }
 
         Ok(())
     }
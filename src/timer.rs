@@ -0,0 +1,84 @@
+use core::time::Duration;
+
+use acpi::{address::GenericAddress, AcpiHandler};
+use bit_field::BitField;
+
+use crate::{AcpiSystem, AcpiSystemError, FixedEvent, Handler};
+
+/// Frequency of the ACPI PM timer, in Hz
+pub const PM_TIMER_FREQUENCY: u64 = 3_579_545;
+
+/// Converts PM timer ticks into a [Duration]
+pub fn pm_timer_ticks_to_duration(ticks: u64) -> Duration {
+    let nanos = ticks as u128 * 1_000_000_000 / PM_TIMER_FREQUENCY as u128;
+
+    Duration::from_nanos(nanos as u64)
+}
+
+impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
+    fn pm_timer_block(&self) -> Result<GenericAddress, AcpiSystemError> {
+        self.fadt
+            .pm_timer_block()?
+            .filter(|block| block.address != 0)
+            .ok_or(AcpiSystemError::PmTimerNotSupported)
+    }
+
+    /// Returns the width of the PM timer counter in bits, either 24 or 32 (TMR_VAL_EXT)
+    pub fn pm_timer_width(&self) -> usize {
+        let flags = self.fadt.flags;
+        if flags.timer_is_32_bit() {
+            32
+        } else {
+            24
+        }
+    }
+
+    /// Reads the current value of the PM timer counter
+    pub fn pm_timer_read(&self) -> Result<u32, AcpiSystemError> {
+        let block = self.pm_timer_block()?;
+        let value = Self::read_address(block)?;
+
+        Ok(value.get_bits(0..self.pm_timer_width()) as u32)
+    }
+
+    /// Returns the number of ticks between two PM timer counter values, assuming the counter has
+    /// wrapped around at most once between them
+    pub fn pm_timer_elapsed(&self, start: u32, end: u32) -> u32 {
+        let mask = (u32::MAX as u64 >> (32 - self.pm_timer_width())) as u32;
+
+        end.wrapping_sub(start) & mask
+    }
+
+    /// Enables the timer fixed event (TMR_STS), which fires each time the most significant bit
+    /// of the counter changes, so that [AcpiSystem::pm_timer_read_extended] doesn't wrap around
+    pub fn enable_pm_timer_overflow_tracking(&mut self) -> Result<(), AcpiSystemError> {
+        let msb = self.pm_timer_width() - 1;
+
+        // Count the half-periods from the current state of the counter
+        self.pm_timer_half_periods = self.pm_timer_read()?.get_bit(msb) as u64;
+
+        log::info!("Enable ACPI event: {}", FixedEvent::TIMER.name);
+        FixedEvent::TIMER.status_register.clear_status(self)?;
+        FixedEvent::TIMER.enable_register.set(self, true)
+    }
+
+    /// Reads the PM timer counter extended to 64 bits in software. Requires overflow tracking to
+    /// be enabled through [AcpiSystem::enable_pm_timer_overflow_tracking].
+    pub fn pm_timer_read_extended(&self) -> Result<u64, AcpiSystemError> {
+        let msb = self.pm_timer_width() - 1;
+        let counter = self.pm_timer_read()?;
+
+        // The MSB flip may not have been handled yet, the half-period count is behind then
+        let mut half_periods = self.pm_timer_half_periods;
+        if counter.get_bit(msb) != half_periods.get_bit(0) {
+            half_periods += 1;
+        }
+
+        Ok((half_periods << msb) | counter.get_bits(0..msb) as u64)
+    }
+
+    // Called from the SCI handler on TMR_STS
+    pub(crate) fn handle_pm_timer_overflow(&mut self) {
+        self.pm_timer_half_periods += 1;
+    }
+}
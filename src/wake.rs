@@ -0,0 +1,255 @@
+use acpi::AcpiHandler;
+use alloc::{vec, vec::Vec};
+use aml::{value::Args, AmlName, AmlValue};
+
+use crate::{
+    hardware::{AcpiBitRegister, AcpiRegister},
+    AcpiSleepState, AcpiSystem, AcpiSystemError, FixedEvent, Handler,
+};
+
+// Device Sleep Wake: arg0 - enable, arg1 - target system state, arg2 - target device state
+const NAME_DEVICE_SLEEP_WAKE: &str = "_DSW";
+// Power State Wake (deprecated in favor of _DSW): arg0 - enable
+const NAME_POWER_STATE_WAKE: &str = "_PSW";
+const NAME_POWER_RESOURCES_FOR_WAKE: &str = "_PRW";
+
+// Deepest device state from which the device can wake the system from Sx, by sleep state
+const SLEEP_WAKE_STATE_NAMES: &[&str] = &["_S0W", "_S1W", "_S2W", "_S3W", "_S4W"];
+// Shallowest device state the device can be in while the system is in Sx, from S1
+const SLEEP_DEVICE_STATE_NAMES: &[&str] = &["_S1D", "_S2D", "_S3D", "_S4D"];
+// D0, used when the firmware doesn't describe the device state for the sleep state
+const DEVICE_STATE_D0: u64 = 0;
+
+/// Event which has woken the system up
+#[derive(Clone, Debug, PartialEq)]
+pub enum WakeSource {
+    PowerButton,
+    SleepButton,
+    Rtc,
+    PciExpress,
+    /// A wake GPE, along with the wake-enabled device whose _PRW refers to it
+    Gpe {
+        gpe_number: u16,
+        device: Option<AmlName>,
+    },
+}
+
+/// Describes the wake-up of the system from a sleep state
+#[derive(Clone, Debug)]
+pub struct WakeReason {
+    /// The sleep state the system has left
+    pub state: AcpiSleepState,
+    /// Events which were pending when the system woke up. Empty if the reason is unknown.
+    pub sources: Vec<WakeSource>,
+}
+
+pub(crate) struct WakeDevice {
+    pub(crate) path: AmlName,
+    pub(crate) gpe_number: u16,
+}
+
+impl<'a, H: Handler + AcpiHandler + 'a> AcpiSystem<'a, H> {
+    /// Allows the device to wake the system up from the given sleep state. The device's wake GPE
+    /// and deepest wake-capable sleep state are taken from its _PRW, and the device is armed
+    /// through _DSW (or _PSW for older firmware). The device state given to _DSW comes from the
+    /// device's _SxW (or _SxD), so the device is expected to be put into that state.
+    pub fn enable_device_wake(
+        &mut self,
+        aml_path: &str,
+        sleep_state: AcpiSleepState,
+    ) -> Result<(), AcpiSystemError> {
+        let device = AmlName::from_str(aml_path)?;
+        let (gpe_number, deepest_sleep_state) = self.device_wake_info(&device)?;
+
+        if sleep_state as u8 > deepest_sleep_state {
+            log::warn!(
+                "{} cannot wake the system from {:?}, S{} at most",
+                device,
+                sleep_state,
+                deepest_sleep_state
+            );
+            return Err(AcpiSystemError::UnsupportedWakeState(sleep_state));
+        }
+
+        log::info!("Enable wake: {} (GPE #{:#x})", device, gpe_number);
+        self.arm_device_wake(&device, Some(sleep_state))?;
+
+        self.set_gpe_wake_enabled(gpe_number, true)?;
+
+        self.wake_devices.retain(|wake| wake.path != device);
+        self.wake_devices.push(WakeDevice {
+            path: device,
+            gpe_number,
+        });
+
+        Ok(())
+    }
+
+    /// Prevents the device from waking the system up
+    pub fn disable_device_wake(&mut self, aml_path: &str) -> Result<(), AcpiSystemError> {
+        let device = AmlName::from_str(aml_path)?;
+        let Some(index) = self
+            .wake_devices
+            .iter()
+            .position(|wake| wake.path == device)
+        else {
+            return Ok(());
+        };
+        let wake = self.wake_devices.remove(index);
+
+        log::info!("Disable wake: {} (GPE #{:#x})", device, wake.gpe_number);
+        self.arm_device_wake(&device, None)?;
+
+        // Other devices may share the GPE
+        if self
+            .wake_devices
+            .iter()
+            .any(|other| other.gpe_number == wake.gpe_number)
+        {
+            return Ok(());
+        }
+
+        self.set_gpe_wake_enabled(wake.gpe_number, false)
+    }
+
+    // Collects the wake events from the PM1x_STS and GPEx_STS registers, must be done before
+    // they're cleared by the wake sequence
+    pub(crate) fn read_wake_sources(&self) -> Result<Vec<WakeSource>, AcpiSystemError> {
+        let mut sources = vec![];
+        // Hardware-reduced platforms don't have the fixed wake events
+        if !self.is_hardware_reduced() {
+            let status = self.read_register(AcpiRegister::Pm1Status)?;
+
+            let fixed_sources = [
+                (
+                    &FixedEvent::POWER_BUTTON.status_register,
+                    WakeSource::PowerButton,
+                ),
+                (
+                    &FixedEvent::SLEEP_BUTTON.status_register,
+                    WakeSource::SleepButton,
+                ),
+                (&FixedEvent::RTC.status_register, WakeSource::Rtc),
+                (&AcpiBitRegister::PCIEXP_WAKE_STATUS, WakeSource::PciExpress),
+            ];
+            for (register, source) in fixed_sources {
+                if register.get_from_raw(status) {
+                    sources.push(source);
+                }
+            }
+        }
+
+        for gpe_number in self.active_wake_gpes()? {
+            let device = self
+                .wake_devices
+                .iter()
+                .find(|wake| wake.gpe_number == gpe_number)
+                .map(|wake| wake.path.clone());
+
+            sources.push(WakeSource::Gpe { gpe_number, device });
+        }
+
+        Ok(sources)
+    }
+
+    // Evaluates _PRW of the device, returning its wake GPE and the deepest sleep state it can
+    // wake the system from
+    fn device_wake_info(&mut self, device: &AmlName) -> Result<(u16, u8), AcpiSystemError> {
+        let path = AmlName::from_str(NAME_POWER_RESOURCES_FOR_WAKE)
+            .unwrap()
+            .resolve(device)?;
+        let Some(AmlValue::Package(prw)) = self.evaluate_object(&path)? else {
+            return Err(AcpiSystemError::InvalidWakeDevice);
+        };
+        let prw = prw.lock();
+
+        if prw.len() < 2 {
+            return Err(AcpiSystemError::InvalidWakeDevice);
+        }
+
+        // The event info is either a GPE number within the FADT blocks, or a package of
+        // (GPE block device, GPE index within the block)
+        let gpe_number = match &prw[0] {
+            AmlValue::Integer(gpe_number) => *gpe_number as u16,
+            AmlValue::Package(event_info) => {
+                let event_info = event_info.lock();
+                let (Some(AmlValue::String(block_device)), Some(index)) =
+                    (event_info.first(), event_info.get(1))
+                else {
+                    return Err(AcpiSystemError::InvalidWakeDevice);
+                };
+                let block_device = AmlName::from_str(block_device)?.resolve(device)?;
+                let index = index.as_integer(&self.aml_context)? as u16;
+                let base = self
+                    .gpe_block_device_base(&block_device)
+                    .ok_or(AcpiSystemError::InvalidWakeDevice)?;
+
+                base + index
+            }
+            _ => return Err(AcpiSystemError::InvalidWakeDevice),
+        };
+        let deepest_sleep_state = prw[1].as_integer(&self.aml_context)? as u8;
+
+        Ok((gpe_number, deepest_sleep_state))
+    }
+
+    // Runs _DSW or _PSW of the device. `None` disarms the device.
+    fn arm_device_wake(
+        &mut self,
+        device: &AmlName,
+        sleep_state: Option<AcpiSleepState>,
+    ) -> Result<(), AcpiSystemError> {
+        let enable = sleep_state.is_some() as u64;
+        let target_state = sleep_state.map_or(0, |state| state as u64);
+        let device_state = match sleep_state {
+            Some(sleep_state) => self.device_sleep_state(device, sleep_state)?,
+            None => DEVICE_STATE_D0,
+        };
+
+        let path = AmlName::from_str(NAME_DEVICE_SLEEP_WAKE)
+            .unwrap()
+            .resolve(device)?;
+        let args = Args::from_list(vec![
+            AmlValue::Integer(enable),
+            AmlValue::Integer(target_state),
+            AmlValue::Integer(device_state),
+        ])
+        .unwrap();
+
+        if self.invoke_optional_method(&path, args)? {
+            return Ok(());
+        }
+
+        let path = AmlName::from_str(NAME_POWER_STATE_WAKE)
+            .unwrap()
+            .resolve(device)?;
+        let args = Args::from_list(vec![AmlValue::Integer(enable)]).unwrap();
+
+        self.invoke_optional_method(&path, args)?;
+        Ok(())
+    }
+
+    // Returns the device state the device enters for the system sleep state, preferring the
+    // deepest one it can still wake the system from
+    fn device_sleep_state(
+        &mut self,
+        device: &AmlName,
+        sleep_state: AcpiSleepState,
+    ) -> Result<u64, AcpiSystemError> {
+        let index = sleep_state as usize;
+        let names = [
+            SLEEP_WAKE_STATE_NAMES.get(index),
+            index
+                .checked_sub(1)
+                .and_then(|index| SLEEP_DEVICE_STATE_NAMES.get(index)),
+        ];
+        for name in names.into_iter().flatten() {
+            let path = AmlName::from_str(name).unwrap().resolve(device)?;
+            if let Some(value) = self.evaluate_object(&path)? {
+                return Ok(value.as_integer(&self.aml_context)?);
+            }
+        }
+
+        Ok(DEVICE_STATE_D0)
+    }
+}